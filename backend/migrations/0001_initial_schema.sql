@@ -0,0 +1,134 @@
+-- Initial schema, matching what init_db created before migrations existed.
+--
+-- Every statement is IF NOT EXISTS / OR IGNORE so this also applies cleanly
+-- to databases that already have some of these tables (e.g. one built from
+-- the shipped SQL dump).
+--
+-- Notes about types in SQLite:
+-- - `INTEGER PRIMARY KEY` automatically uses AUTOINCREMENT
+-- - `TEXT` is used for text fields
+-- - timestamps are stored as TEXT with DEFAULT (datetime('now'))
+-- - `options` and other lists are stored as JSON text in `TEXT` columns
+
+-- Source vocabulary entries
+CREATE TABLE IF NOT EXISTS entries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    list_index INTEGER,
+    kanji TEXT,
+    kana TEXT,
+    meaning TEXT
+);
+
+-- Quizzes: containers grouping questions
+CREATE TABLE IF NOT EXISTS quizzes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    title TEXT NOT NULL,
+    description TEXT,
+    jlpt_level TEXT,
+    created_at TEXT DEFAULT (datetime('now')),
+    updated_at TEXT DEFAULT (datetime('now')),
+    deleted_at TEXT,
+    version INTEGER NOT NULL DEFAULT 1,
+    status TEXT NOT NULL DEFAULT 'draft'
+);
+
+-- JLPT level mapping (1 -> n5, 2 -> n4, ..., 5 -> n1)
+CREATE TABLE IF NOT EXISTS n_level (
+    id INTEGER PRIMARY KEY,
+    level TEXT NOT NULL
+);
+
+INSERT OR IGNORE INTO n_level (id, level) VALUES
+    (1, 'n5'),
+    (2, 'n4'),
+    (3, 'n3'),
+    (4, 'n2'),
+    (5, 'n1');
+
+-- Questions, both generated from entries (entry_id, q_type, level, chapter)
+-- and written for a quiz (quiz_id)
+CREATE TABLE IF NOT EXISTS questions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    entry_id INTEGER,
+    quiz_id INTEGER,
+    q_type TEXT,
+    prompt TEXT,
+    correct_answer TEXT,
+    options TEXT,
+    correct_index INTEGER,
+    question_type TEXT DEFAULT 'single',
+    correct_answers TEXT,
+    answer_text TEXT,
+    exact_script INTEGER NOT NULL DEFAULT 0,
+    level INTEGER,
+    chapter INTEGER,
+    reading TEXT,
+    audio_url TEXT,
+    difficulty INTEGER DEFAULT 3,
+    position INTEGER,
+    created_at TEXT DEFAULT (datetime('now')),
+    FOREIGN KEY (entry_id) REFERENCES entries(id) ON DELETE CASCADE,
+    FOREIGN KEY (quiz_id) REFERENCES quizzes(id) ON DELETE SET NULL,
+    FOREIGN KEY (level) REFERENCES n_level(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_entry_id ON questions(entry_id);
+
+-- Generated tests (questions JSON)
+CREATE TABLE IF NOT EXISTS tests (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    title TEXT,
+    questions TEXT,
+    created_at TEXT DEFAULT (datetime('now'))
+);
+
+-- Every scored quiz submission; answers as a JSON array
+CREATE TABLE IF NOT EXISTS attempts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    quiz_id INTEGER NOT NULL,
+    score INTEGER NOT NULL,
+    total INTEGER NOT NULL,
+    answers TEXT NOT NULL,
+    created_at TEXT DEFAULT (datetime('now')),
+    FOREIGN KEY (quiz_id) REFERENCES quizzes(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_attempts_quiz_id ON attempts(quiz_id);
+
+-- Tags (unique, lowercased topic names) and the quiz_tags join table
+CREATE TABLE IF NOT EXISTS tags (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS quiz_tags (
+    quiz_id INTEGER NOT NULL,
+    tag_id INTEGER NOT NULL,
+    PRIMARY KEY (quiz_id, tag_id),
+    FOREIGN KEY (quiz_id) REFERENCES quizzes(id) ON DELETE CASCADE,
+    FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+);
+
+-- Idempotency-Key values already used by create_quiz, with the quiz they
+-- created; rows older than 24 hours are ignored and purged
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    key TEXT PRIMARY KEY,
+    quiz_id INTEGER NOT NULL,
+    created_at TEXT DEFAULT (datetime('now'))
+);
+
+-- Spaced-repetition state of each question per learner session (SM-2);
+-- next_review_at uses the same format as datetime('now') so it compares
+CREATE TABLE IF NOT EXISTS review_state (
+    session TEXT NOT NULL,
+    question_id INTEGER NOT NULL,
+    ease_factor REAL NOT NULL,
+    interval_days INTEGER NOT NULL,
+    repetitions INTEGER NOT NULL,
+    next_review_at TEXT NOT NULL,
+    updated_at TEXT DEFAULT (datetime('now')),
+    PRIMARY KEY (session, question_id),
+    FOREIGN KEY (question_id) REFERENCES questions(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_review_state_due ON review_state(session, next_review_at);
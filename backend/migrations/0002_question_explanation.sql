@@ -0,0 +1,2 @@
+-- Why the correct answer is correct, shown to learners after they submit
+ALTER TABLE questions ADD COLUMN explanation TEXT;
@@ -0,0 +1,3 @@
+-- Milliseconds spent on each question of an attempt, as a JSON array in
+-- question order; NULL when the client didn't report timings
+ALTER TABLE attempts ADD COLUMN timings TEXT;
@@ -0,0 +1,3 @@
+-- Example sentence using the vocab item, and its reading in kana
+ALTER TABLE questions ADD COLUMN example_sentence TEXT;
+ALTER TABLE questions ADD COLUMN example_reading TEXT;
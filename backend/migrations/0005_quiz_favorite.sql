@@ -0,0 +1,2 @@
+-- Starred by the learner for quick access
+ALTER TABLE quizzes ADD COLUMN favorite INTEGER NOT NULL DEFAULT 0;
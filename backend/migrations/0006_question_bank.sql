@@ -0,0 +1,25 @@
+-- Which questions each quiz shows, and in what order, so a question can be
+-- reused across quizzes.
+--
+-- questions.quiz_id now only names the quiz a question was written for: it
+-- is deleted along with that quiz and never attached anywhere else. Bank
+-- questions (neither quiz_id nor entry_id) belong to no quiz and can be
+-- attached to any number of them. questions.position is superseded by
+-- quiz_questions.position and no longer written.
+CREATE TABLE IF NOT EXISTS quiz_questions (
+    quiz_id INTEGER NOT NULL,
+    question_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (quiz_id, question_id),
+    FOREIGN KEY (quiz_id) REFERENCES quizzes(id) ON DELETE CASCADE,
+    FOREIGN KEY (question_id) REFERENCES questions(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_quiz_questions_question_id ON quiz_questions(question_id);
+
+-- Every existing quiz question becomes a member of its quiz, in its
+-- current order
+INSERT OR IGNORE INTO quiz_questions (quiz_id, question_id, position)
+SELECT quiz_id, id, COALESCE(position, id)
+FROM questions
+WHERE quiz_id IN (SELECT id FROM quizzes);
@@ -0,0 +1,5 @@
+-- SHA-256 (hex) of the owner token handed out when the quiz was created;
+-- editing, deleting or publishing the quiz requires the token. NULL for
+-- quizzes without an owner (created before tokens, imported or seeded),
+-- which anyone may still change.
+ALTER TABLE quizzes ADD COLUMN owner_token_hash TEXT;
@@ -0,0 +1,3 @@
+-- Owner token handed out by the create an Idempotency-Key belongs to, so a
+-- retry within the key's 24 hours gets it again; purged with the key
+ALTER TABLE idempotency_keys ADD COLUMN owner_token TEXT;
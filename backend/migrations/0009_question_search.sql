@@ -0,0 +1,33 @@
+-- Full-text index over question text and readings for GET /api/search.
+--
+-- The trigram tokenizer matches any substring of three or more characters,
+-- which suits Japanese (no spaces between words) as well as romaji. It is
+-- an external-content table over questions, kept in sync by the triggers
+-- below, so the text itself isn't stored twice.
+CREATE VIRTUAL TABLE IF NOT EXISTS questions_fts USING fts5(
+    prompt,
+    reading,
+    content = 'questions',
+    content_rowid = 'id',
+    tokenize = 'trigram'
+);
+
+CREATE TRIGGER IF NOT EXISTS questions_fts_insert AFTER INSERT ON questions BEGIN
+    INSERT INTO questions_fts (rowid, prompt, reading)
+    VALUES (new.id, new.prompt, new.reading);
+END;
+
+CREATE TRIGGER IF NOT EXISTS questions_fts_delete AFTER DELETE ON questions BEGIN
+    INSERT INTO questions_fts (questions_fts, rowid, prompt, reading)
+    VALUES ('delete', old.id, old.prompt, old.reading);
+END;
+
+CREATE TRIGGER IF NOT EXISTS questions_fts_update AFTER UPDATE OF prompt, reading ON questions BEGIN
+    INSERT INTO questions_fts (questions_fts, rowid, prompt, reading)
+    VALUES ('delete', old.id, old.prompt, old.reading);
+    INSERT INTO questions_fts (rowid, prompt, reading)
+    VALUES (new.id, new.prompt, new.reading);
+END;
+
+-- Index the questions that already exist
+INSERT INTO questions_fts (questions_fts) VALUES ('rebuild');
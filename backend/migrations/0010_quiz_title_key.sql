@@ -0,0 +1,6 @@
+-- Normalized title (see tools::title_key), indexed so ?unique_title=true
+-- finds a duplicate without reading every quiz. SQL has no NFKC, so
+-- existing quizzes are filled in by db::init_db after migrating.
+ALTER TABLE quizzes ADD COLUMN title_key TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_quizzes_title_key ON quizzes(title_key);
@@ -0,0 +1,4 @@
+-- Owner tokens are only ever stored hashed: a retried create no longer gets
+-- the token again (see create_quiz), so the plaintext copy kept with its
+-- Idempotency-Key since 0008 goes
+ALTER TABLE idempotency_keys DROP COLUMN owner_token;
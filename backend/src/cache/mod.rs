@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Per-quiz question counts for the summaries listing, kept in app data.
+///
+/// Handlers that add, change or remove a quiz's questions call
+/// `invalidate` once their transaction has committed; the next listing
+/// counts that quiz in the database again.
+///
+/// A listing that misses reads the database without holding the lock, so
+/// a write can commit and invalidate in between, leaving the listing with
+/// a stale count. Every invalidation bumps `generation`, and `fill` drops
+/// counts read before the latest bump, so a stale count is never cached.
+pub struct QuestionCounts {
+    state: RwLock<CountsState>,
+}
+
+struct CountsState {
+    counts: HashMap<i64, usize>,
+    generation: u64,
+}
+
+impl QuestionCounts {
+    pub fn new() -> Self {
+        QuestionCounts {
+            state: RwLock::new(CountsState {
+                counts: HashMap::new(),
+                generation: 0,
+            }),
+        }
+    }
+
+    /// Cached counts for those of `quiz_ids` that have one, and the
+    /// generation to pass to `fill` with the counts of the rest
+    pub fn get(&self, quiz_ids: &[i64]) -> (HashMap<i64, usize>, u64) {
+        let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+        let hits = quiz_ids
+            .iter()
+            .filter_map(|id| state.counts.get(id).map(|count| (*id, *count)))
+            .collect();
+        (hits, state.generation)
+    }
+
+    /// Cache counts read from the database after `get` returned
+    /// `generation`, unless a quiz was invalidated since
+    pub fn fill(&self, generation: u64, counts: &HashMap<i64, usize>) {
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        if state.generation == generation {
+            state.counts.extend(counts);
+        }
+    }
+
+    /// Forget the count of `quiz_id`, after its questions changed
+    pub fn invalidate(&self, quiz_id: i64) {
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        state.counts.remove(&quiz_id);
+        state.generation += 1;
+    }
+}
@@ -0,0 +1,164 @@
+use std::fmt::Display;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Database opened when `DATABASE_URL` is unset
+const DEFAULT_DATABASE_URL: &str = "sqlite://mimikara_n3_questions.db";
+/// Number of pooled connections used when `DATABASE_MAX_CONNECTIONS` is unset
+const DEFAULT_MAX_CONNECTIONS: u32 = 10;
+/// Connection attempts made when `DATABASE_CONNECT_RETRIES` is unset
+const DEFAULT_CONNECT_ATTEMPTS: u32 = 5;
+/// Checkout timeout used when `DATABASE_CHECKOUT_TIMEOUT_MS` is unset
+const DEFAULT_CHECKOUT_TIMEOUT_MS: u64 = 5000;
+/// Address and port used when `BIND_ADDRESS` / `PORT` are unset
+const DEFAULT_BIND_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+const DEFAULT_PORT: u16 = 8081;
+/// Write requests per minute allowed per client IP when
+/// `RATE_LIMIT_PER_MINUTE` is unset
+const DEFAULT_RATE_LIMIT_PER_MINUTE: u32 = 30;
+/// Largest request body accepted when `MAX_BODY_BYTES` is unset (1 MiB)
+const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024;
+/// Seconds between database health checks when `DATABASE_HEALTH_CHECK_SECS`
+/// is unset
+const DEFAULT_HEALTH_CHECK_SECS: u64 = 10;
+/// Seconds a request may take when `REQUEST_TIMEOUT_SECS` is unset
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+/// Seconds between purge runs when `PURGE_INTERVAL_SECS` is unset (1 hour)
+const DEFAULT_PURGE_INTERVAL_SECS: u64 = 60 * 60;
+/// Days a soft-deleted quiz is kept when `PURGE_RETENTION_DAYS` is unset
+const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+/// How to open the SQLite pool (see `db::connect`)
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    /// `DATABASE_URL`, a `sqlite:` URL
+    pub url: String,
+    /// `DATABASE_MAX_CONNECTIONS`, the pool size (at least 1)
+    pub max_connections: u32,
+    /// `DATABASE_CONNECT_RETRIES`, connection attempts at startup (at least 1)
+    pub connect_attempts: u32,
+    /// `DATABASE_CHECKOUT_TIMEOUT_MS`, how long a connection may be used per
+    /// checkout from the pool; zero disables the timeout
+    pub checkout_timeout: Duration,
+    /// `DATABASE_HEALTH_CHECK_SECS`, how often `db::spawn_monitor` checks
+    /// the database is reachable (at least 1 second)
+    pub health_check_interval: Duration,
+}
+
+/// Every tunable of the server, read once from the environment at startup.
+///
+/// Unset variables fall back to the defaults above; a variable that is set
+/// but can't be parsed makes `from_env` fail with a message naming it, so a
+/// typo stops the server instead of being silently ignored. `.env.example`
+/// documents each variable. Shared with handlers as `web::Data<Config>`.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub database: DatabaseConfig,
+    /// `BIND_ADDRESS`, an IP such as `0.0.0.0`
+    pub bind_address: IpAddr,
+    /// `PORT`
+    pub port: u16,
+    /// `ALLOWED_ORIGINS` (comma-separated); `None` allows any origin
+    pub allowed_origins: Option<Vec<String>>,
+    /// `RATE_LIMIT_PER_MINUTE`; 0 disables rate limiting
+    pub rate_limit_per_minute: u32,
+    /// `MAX_BODY_BYTES`, the request body size limit (at least 1)
+    pub max_body_bytes: usize,
+    /// `REQUEST_TIMEOUT_SECS`; `None` (from 0) lets requests run forever
+    pub request_timeout: Option<Duration>,
+    /// `PURGE_INTERVAL_SECS`; `None` (from 0) disables purging
+    pub purge_interval: Option<Duration>,
+    /// `PURGE_RETENTION_DAYS`, how long a soft-deleted quiz can be restored
+    pub purge_retention_days: u32,
+    /// `KANJI_READINGS_PATH`, the file of `readings::KanjiReadings`; `None`
+    /// turns reading warnings off
+    pub kanji_readings_path: Option<PathBuf>,
+}
+
+/// Parse the variable `name`, or return `default` when it is unset
+fn var_or<T>(name: &str, default: T) -> Result<T, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match std::env::var(name) {
+        Ok(v) => v
+            .trim()
+            .parse::<T>()
+            .map_err(|e| format!("{} '{}' is not valid: {}", name, v, e)),
+        Err(_) => Ok(default),
+    }
+}
+
+/// Like `var_or`, but zero is rejected
+fn positive_var_or<T>(name: &str, default: T) -> Result<T, String>
+where
+    T: FromStr + Default + PartialEq,
+    T::Err: Display,
+{
+    let value = var_or(name, default)?;
+    if value == T::default() {
+        return Err(format!("{} must be greater than 0", name));
+    }
+    Ok(value)
+}
+
+/// Parse `ALLOWED_ORIGINS` (comma-separated, e.g.
+/// `http://localhost:3000,https://quiz.example.com`) into a list of origins.
+/// Returns `None` when the variable is unset or contains no origins.
+fn allowed_origins_from_env() -> Option<Vec<String>> {
+    let origins: Vec<String> = std::env::var("ALLOWED_ORIGINS")
+        .ok()?
+        .split(',')
+        .map(|o| o.trim().to_string())
+        .filter(|o| !o.is_empty())
+        .collect();
+    if origins.is_empty() {
+        None
+    } else {
+        Some(origins)
+    }
+}
+
+impl Config {
+    /// Read the configuration from the environment (after `.env` is loaded)
+    pub fn from_env() -> Result<Config, String> {
+        let database = DatabaseConfig {
+            url: std::env::var("DATABASE_URL").unwrap_or_else(|_| DEFAULT_DATABASE_URL.to_string()),
+            max_connections: positive_var_or("DATABASE_MAX_CONNECTIONS", DEFAULT_MAX_CONNECTIONS)?,
+            connect_attempts: positive_var_or(
+                "DATABASE_CONNECT_RETRIES",
+                DEFAULT_CONNECT_ATTEMPTS,
+            )?,
+            checkout_timeout: Duration::from_millis(var_or(
+                "DATABASE_CHECKOUT_TIMEOUT_MS",
+                DEFAULT_CHECKOUT_TIMEOUT_MS,
+            )?),
+            health_check_interval: Duration::from_secs(positive_var_or(
+                "DATABASE_HEALTH_CHECK_SECS",
+                DEFAULT_HEALTH_CHECK_SECS,
+            )?),
+        };
+        let request_timeout_secs = var_or("REQUEST_TIMEOUT_SECS", DEFAULT_REQUEST_TIMEOUT_SECS)?;
+        let purge_interval_secs = var_or("PURGE_INTERVAL_SECS", DEFAULT_PURGE_INTERVAL_SECS)?;
+
+        Ok(Config {
+            database,
+            bind_address: var_or("BIND_ADDRESS", DEFAULT_BIND_ADDRESS)?,
+            port: var_or("PORT", DEFAULT_PORT)?,
+            allowed_origins: allowed_origins_from_env(),
+            rate_limit_per_minute: var_or("RATE_LIMIT_PER_MINUTE", DEFAULT_RATE_LIMIT_PER_MINUTE)?,
+            max_body_bytes: positive_var_or("MAX_BODY_BYTES", DEFAULT_MAX_BODY_BYTES)?,
+            request_timeout: (request_timeout_secs > 0)
+                .then(|| Duration::from_secs(request_timeout_secs)),
+            purge_interval: (purge_interval_secs > 0)
+                .then(|| Duration::from_secs(purge_interval_secs)),
+            purge_retention_days: var_or("PURGE_RETENTION_DAYS", DEFAULT_RETENTION_DAYS)?,
+            kanji_readings_path: std::env::var_os("KANJI_READINGS_PATH")
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from),
+        })
+    }
+}
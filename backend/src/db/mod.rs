@@ -1,183 +1,400 @@
-use sqlx::sqlite::SqlitePool;
+use crate::config::DatabaseConfig;
+use sqlx::migrate::Migrator;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection, SqlitePool, SqlitePoolOptions};
 use sqlx::Row;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-// Note: the previous `connect()` helper was removed because the application
-// uses `SqlitePool::connect` directly in `main.rs`. If a centralized helper
-// is needed later, it can be reintroduced here.
+/// Delay before the first retry; doubled after every failed attempt
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(500);
+/// SQLite VM instructions run between checks of the checkout timeout
+const TIMEOUT_CHECK_OPS: i32 = 1000;
 
-/// Initialize required tables for the application in SQLite.
+/// Make statements on `conn` fail (SQLite reports "interrupted") once
+/// `timeout` has passed from now.
 ///
-/// Notes about type differences for SQLite:
-/// - `INTEGER PRIMARY KEY` automatically uses AUTOINCREMENT
-/// - `TEXT` is used for text fields
-/// - `TIMESTAMP` is stored as TEXT with DEFAULT (datetime('now'))
-/// - `options` is stored as JSON text in a `TEXT` column
-pub async fn init_db(pool: &SqlitePool) -> Result<(), sqlx::Error> {
-    // Create entries table (source data)
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS entries (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            list_index INTEGER,
-            kanji TEXT,
-            kana TEXT,
-            meaning TEXT
-        );
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // Create quizzes table (optional container for grouping questions)
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS quizzes (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            title TEXT NOT NULL,
-            description TEXT,
-            created_at TEXT DEFAULT (datetime('now'))
-        );
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // Create n_level mapping table (1 -> n5, 2 -> n4, ..., 5 -> n1)
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS n_level (
-            id INTEGER PRIMARY KEY,
-            level TEXT NOT NULL
-        );
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // Populate canonical n_level rows (id 1..5)
-    // Use INSERT OR IGNORE so this is idempotent.
-    sqlx::query("INSERT OR IGNORE INTO n_level (id, level) VALUES (?, ?)")
-        .bind(1i32)
-        .bind("n5")
-        .execute(pool)
-        .await?;
-    sqlx::query("INSERT OR IGNORE INTO n_level (id, level) VALUES (?, ?)")
-        .bind(2i32)
-        .bind("n4")
-        .execute(pool)
-        .await?;
-    sqlx::query("INSERT OR IGNORE INTO n_level (id, level) VALUES (?, ?)")
-        .bind(3i32)
-        .bind("n3")
-        .execute(pool)
-        .await?;
-    sqlx::query("INSERT OR IGNORE INTO n_level (id, level) VALUES (?, ?)")
-        .bind(4i32)
-        .bind("n2")
-        .execute(pool)
-        .await?;
-    sqlx::query("INSERT OR IGNORE INTO n_level (id, level) VALUES (?, ?)")
-        .bind(5i32)
-        .bind("n1")
-        .execute(pool)
-        .await?;
+/// SQLite has no server-side statement timeout, so this uses a progress
+/// handler, which SQLite calls while a statement runs. The handler is armed
+/// when a connection is checked out of the pool, not per statement, so the
+/// limit covers everything run in one checkout: each query on the pool, or
+/// a whole transaction.
+async fn arm_checkout_timeout(
+    conn: &mut SqliteConnection,
+    timeout: Duration,
+) -> Result<(), sqlx::Error> {
+    let started = Instant::now();
+    conn.lock_handle()
+        .await?
+        .set_progress_handler(TIMEOUT_CHECK_OPS, move || started.elapsed() < timeout);
+    Ok(())
+}
 
-    // Create questions table (keeps original columns and extends with level & chapter)
-    // - entry_id references entries.id
-    // - quiz_id is optional (can be NULL) and references quizzes.id
-    // - options stored as JSON text in a TEXT column
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS questions (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            entry_id INTEGER,
-            quiz_id INTEGER,
-            q_type TEXT,
-            prompt TEXT,
-            correct_answer TEXT,
-            options TEXT,
-            correct_index INTEGER,
-            level INTEGER,
-            chapter INTEGER,
-            created_at TEXT DEFAULT (datetime('now')),
-            FOREIGN KEY (entry_id) REFERENCES entries(id) ON DELETE CASCADE,
-            FOREIGN KEY (quiz_id) REFERENCES quizzes(id) ON DELETE SET NULL,
-            FOREIGN KEY (level) REFERENCES n_level(id)
-        );
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // Create tests table to store generated tests (questions JSON)
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS tests (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            title TEXT,
-            questions TEXT,
-            created_at TEXT DEFAULT (datetime('now'))
-        );
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // Helpful index for lookups by entry_id
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_entry_id ON questions(entry_id);")
-        .execute(pool)
-        .await?;
+/// Open the shared SQLite connection pool.
+///
+/// Handlers receive the pool through `web::Data<SqlitePool>` and check out a
+/// connection per query, so requests run in parallel instead of queueing on a
+/// single connection. The pool size can be tuned with `DATABASE_MAX_CONNECTIONS`.
+///
+/// Connecting is attempted up to `DATABASE_CONNECT_RETRIES` times (default 5)
+/// with exponential backoff, so a database that is still starting up (or a
+/// volume that is not mounted yet) does not abort the process immediately.
+/// The last error is returned once all attempts are used up.
+///
+/// `sqlite::memory:` gives a throwaway database that lasts for the life of
+/// the pool (shared by all of its connections), e.g. for scratch runs.
+///
+/// Queries are aborted once a connection has been checked out of the pool
+/// for longer than `DATABASE_CHECKOUT_TIMEOUT_MS` (default 5000, 0 turns it
+/// off), so a runaway query fails with a 500 instead of hanging the request.
+/// This is a limit per checkout rather than per statement: statements that
+/// share a connection, such as those of one transaction, share the budget.
+/// Waiting on a locked database is bounded by the same limit.
+///
+/// A malformed `database_url` fails straight away, without retrying, with a
+/// configuration error that names `DATABASE_URL` and the parse problem.
+pub async fn connect(config: &DatabaseConfig) -> Result<SqlitePool, sqlx::Error> {
+    let database_url = config.url.as_str();
+    if !database_url.starts_with("sqlite:") {
+        return Err(sqlx::Error::Configuration(
+            format!(
+                "DATABASE_URL '{}' is not a SQLite URL; it must start with sqlite: (e.g. sqlite://quiz.db)",
+                database_url
+            )
+            .into(),
+        ));
+    }
+    let checkout_timeout = config.checkout_timeout;
+
+    let mut options = SqliteConnectOptions::from_str(database_url).map_err(|e| {
+        sqlx::Error::Configuration(
+            format!(
+                "DATABASE_URL '{}' is not a valid SQLite URL: {}",
+                database_url, e
+            )
+            .into(),
+        )
+    })?;
+    if !checkout_timeout.is_zero() {
+        options = options.busy_timeout(checkout_timeout);
+    }
+
+    let in_memory = database_url.contains(":memory:") || database_url.contains("mode=memory");
 
-    // Add missing columns to questions table if they don't exist
-    // This handles the case where database was created from SQL file
-    
-    // Helper function to check if column exists in a table
-    // Note: This function is only called with hardcoded table names during initialization,
-    // not with user input. Table names are validated via allowlist.
-    async fn column_exists(pool: &SqlitePool, table: &str, column: &str) -> bool {
-        // Validate table name against allowlist to prevent SQL injection
-        const ALLOWED_TABLES: &[&str] = &["questions", "entries", "quizzes", "tests", "n_level"];
-        if !ALLOWED_TABLES.contains(&table) {
-            return false;
+    let max_connections = config.max_connections;
+    let attempts = config.connect_attempts;
+
+    let mut delay = INITIAL_RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        let mut pool_options = SqlitePoolOptions::new().max_connections(max_connections);
+        if in_memory {
+            // An in-memory database only lives as long as some connection to
+            // it is open, so one is kept open and never recycled.
+            pool_options = pool_options
+                .min_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None);
         }
-        
-        // For SQLite, we use PRAGMA table_info
-        let query = format!("PRAGMA table_info({})", table);
-        if let Ok(rows) = sqlx::query(&query)
-            .fetch_all(pool)
-            .await 
-        {
-            for row in rows {
-                if let Ok(col_name) = row.try_get::<String, _>("name") {
-                    if col_name == column {
-                        return true;
+        if !checkout_timeout.is_zero() {
+            // The clock restarts every time a connection is handed out
+            pool_options = pool_options
+                .after_connect(move |conn, _| {
+                    Box::pin(arm_checkout_timeout(conn, checkout_timeout))
+                })
+                .before_acquire(move |conn, _| {
+                    Box::pin(async move {
+                        arm_checkout_timeout(conn, checkout_timeout).await?;
+                        Ok(true)
+                    })
+                });
+        }
+        match pool_options.connect_with(options.clone()).await {
+            Ok(pool) => return Ok(pool),
+            Err(e) if attempt < attempts => {
+                log::warn!(
+                    "Database connection attempt {}/{} failed: {}; retrying in {:?}",
+                    attempt,
+                    attempts,
+                    e,
+                    delay
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Whether the database is reachable, as last seen by `spawn_monitor` or a
+/// `/health` check. Shared with handlers as `web::Data<DbHealth>`.
+#[derive(Debug)]
+pub struct DbHealth {
+    up: AtomicBool,
+}
+
+impl DbHealth {
+    /// Starts out up: `connect` has just succeeded
+    pub fn new() -> Self {
+        DbHealth {
+            up: AtomicBool::new(true),
+        }
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.up.load(Ordering::Relaxed)
+    }
+
+    /// Record the outcome of a check, logging when the connection is lost
+    /// and when it comes back
+    pub fn record(&self, up: bool) {
+        let was_up = self.up.swap(up, Ordering::Relaxed);
+        match (was_up, up) {
+            (true, false) => log::error!("Lost the database connection"),
+            (false, true) => log::info!("Database connection restored"),
+            _ => {}
+        }
+    }
+}
+
+/// Spawn the background task that checks the database answers `SELECT 1`
+/// every `interval` and records the outcome in `health`.
+///
+/// Once a check fails it retries sooner, backing off from
+/// `INITIAL_RETRY_DELAY` up to `interval`. Each check borrows a connection
+/// from the pool, which replaces connections that have gone bad, so a
+/// successful retry means the pool has reconnected and handlers work again.
+pub fn spawn_monitor(pool: SqlitePool, health: Arc<DbHealth>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut delay = interval;
+        loop {
+            tokio::time::sleep(delay).await;
+            match sqlx::query("SELECT 1").execute(&pool).await {
+                Ok(_) => {
+                    health.record(true);
+                    delay = interval;
+                }
+                Err(e) => {
+                    if health.is_up() {
+                        log::warn!("Database health check failed: {}", e);
                     }
+                    health.record(false);
+                    delay = if delay >= interval {
+                        INITIAL_RETRY_DELAY.min(interval)
+                    } else {
+                        (delay * 2).min(interval)
+                    };
                 }
             }
         }
-        false
+    });
+}
+
+/// Schema migrations, embedded from `backend/migrations` at compile time.
+///
+/// Applied versions are recorded in `_sqlx_migrations`, so each runs once.
+/// Schema changes ship as a new numbered file; an applied migration must
+/// never be edited, since its checksum is verified on startup.
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// Bring the database schema up to date: upgrade a database from before
+/// migrations existed (see `upgrade_legacy_schema`), then run any pending
+/// migrations and fill in what they can't compute in SQL.
+pub async fn init_db(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    if !table_exists(pool, "_sqlx_migrations").await? {
+        upgrade_legacy_schema(pool).await?;
+    }
+    MIGRATOR.run(pool).await?;
+    backfill_title_keys(pool).await?;
+    Ok(())
+}
+
+/// Set `title_key` of quizzes created before it existed (see migration
+/// 0010). New quizzes get theirs on insert, so after the first run this
+/// finds nothing.
+async fn backfill_title_keys(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let rows: Vec<(i64, String)> =
+        sqlx::query_as("SELECT id, title FROM quizzes WHERE title_key IS NULL")
+            .fetch_all(pool)
+            .await?;
+    for (id, title) in rows {
+        sqlx::query("UPDATE quizzes SET title_key = ? WHERE id = ?")
+            .bind(crate::tools::title_key(&title))
+            .bind(id)
+            .execute(pool)
+            .await?;
     }
+    Ok(())
+}
 
+async fn table_exists(pool: &SqlitePool, table: &str) -> Result<bool, sqlx::Error> {
+    let found: Option<i64> =
+        sqlx::query_scalar("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?")
+            .bind(table)
+            .fetch_optional(pool)
+            .await?;
+    Ok(found.is_some())
+}
+
+/// Whether `table` exists but lacks `column`.
+/// Note: This function is only called with hardcoded table names,
+/// not with user input. Table names are validated via allowlist.
+async fn missing_column(pool: &SqlitePool, table: &str, column: &str) -> bool {
+    // Validate table name against allowlist to prevent SQL injection
+    const ALLOWED_TABLES: &[&str] = &["questions", "entries", "quizzes", "tests", "n_level", "attempts"];
+    if !ALLOWED_TABLES.contains(&table) {
+        return false;
+    }
+
+    // For SQLite, we use PRAGMA table_info; a missing table has no rows
+    let query = format!("PRAGMA table_info({})", table);
+    match sqlx::query(&query).fetch_all(pool).await {
+        Ok(rows) if !rows.is_empty() => !rows
+            .iter()
+            .any(|row| row.try_get::<String, _>("name").is_ok_and(|name| name == column)),
+        _ => false,
+    }
+}
+
+/// Add the columns that the initial migration's tables have to a database
+/// created before migrations existed (by an older `init_db`, or from the
+/// shipped SQL dump), where `CREATE TABLE IF NOT EXISTS` left the old tables
+/// as they were. Tables that don't exist yet are left to the migration.
+async fn upgrade_legacy_schema(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     // Add quiz_id column if missing
-    if !column_exists(pool, "questions", "quiz_id").await {
+    if missing_column(pool, "questions", "quiz_id").await {
         sqlx::query("ALTER TABLE questions ADD COLUMN quiz_id INTEGER REFERENCES quizzes(id) ON DELETE SET NULL")
             .execute(pool)
             .await?;
     }
 
     // Add level column if missing
-    if !column_exists(pool, "questions", "level").await {
+    if missing_column(pool, "questions", "level").await {
         sqlx::query("ALTER TABLE questions ADD COLUMN level INTEGER REFERENCES n_level(id)")
             .execute(pool)
             .await?;
     }
 
     // Add chapter column if missing
-    if !column_exists(pool, "questions", "chapter").await {
+    if missing_column(pool, "questions", "chapter").await {
         sqlx::query("ALTER TABLE questions ADD COLUMN chapter INTEGER")
             .execute(pool)
             .await?;
     }
 
+    // Add reading column (kana reading / furigana) if missing
+    if missing_column(pool, "questions", "reading").await {
+        sqlx::query("ALTER TABLE questions ADD COLUMN reading TEXT")
+            .execute(pool)
+            .await?;
+    }
+
+    // Add difficulty column (1-5, default 3) to questions if missing
+    if missing_column(pool, "questions", "difficulty").await {
+        sqlx::query("ALTER TABLE questions ADD COLUMN difficulty INTEGER DEFAULT 3")
+            .execute(pool)
+            .await?;
+    }
+
+    // Add question_type and correct_answers (JSON array of option indices)
+    // to questions if missing, seeding correct_answers from correct_index
+    if missing_column(pool, "questions", "question_type").await {
+        sqlx::query("ALTER TABLE questions ADD COLUMN question_type TEXT DEFAULT 'single'")
+            .execute(pool)
+            .await?;
+    }
+    if missing_column(pool, "questions", "correct_answers").await {
+        sqlx::query("ALTER TABLE questions ADD COLUMN correct_answers TEXT")
+            .execute(pool)
+            .await?;
+        sqlx::query(
+            "UPDATE questions SET correct_answers = json_array(correct_index) WHERE correct_index IS NOT NULL",
+        )
+        .execute(pool)
+        .await?;
+    }
+
+    // Add answer_text (JSON array of accepted answers for fill-in-the-blank
+    // questions) to questions if missing
+    if missing_column(pool, "questions", "answer_text").await {
+        sqlx::query("ALTER TABLE questions ADD COLUMN answer_text TEXT")
+            .execute(pool)
+            .await?;
+    }
+
+    // Add exact_script (text answers must match hiragana/katakana exactly)
+    // to questions if missing
+    if missing_column(pool, "questions", "exact_script").await {
+        sqlx::query("ALTER TABLE questions ADD COLUMN exact_script INTEGER NOT NULL DEFAULT 0")
+            .execute(pool)
+            .await?;
+    }
+
+    // Add audio_url (pronunciation clip for listening practice) to questions
+    // if missing
+    if missing_column(pool, "questions", "audio_url").await {
+        sqlx::query("ALTER TABLE questions ADD COLUMN audio_url TEXT")
+            .execute(pool)
+            .await?;
+    }
+
+    // Add position column (display order within a quiz) to questions if
+    // missing; existing questions keep their insertion order
+    if missing_column(pool, "questions", "position").await {
+        sqlx::query("ALTER TABLE questions ADD COLUMN position INTEGER")
+            .execute(pool)
+            .await?;
+        sqlx::query("UPDATE questions SET position = id WHERE position IS NULL")
+            .execute(pool)
+            .await?;
+    }
+
+    // Add jlpt_level column (N5..N1 tag) to quizzes if missing
+    if missing_column(pool, "quizzes", "jlpt_level").await {
+        sqlx::query("ALTER TABLE quizzes ADD COLUMN jlpt_level TEXT")
+            .execute(pool)
+            .await?;
+    }
+
+    // Add deleted_at column (soft delete timestamp) to quizzes if missing
+    if missing_column(pool, "quizzes", "deleted_at").await {
+        sqlx::query("ALTER TABLE quizzes ADD COLUMN deleted_at TEXT")
+            .execute(pool)
+            .await?;
+    }
+
+    // Add version column (optimistic concurrency counter) to quizzes if missing
+    if missing_column(pool, "quizzes", "version").await {
+        sqlx::query("ALTER TABLE quizzes ADD COLUMN version INTEGER NOT NULL DEFAULT 1")
+            .execute(pool)
+            .await?;
+    }
+
+    // Add status column ('draft' or 'published') to quizzes if missing. New
+    // quizzes start as drafts, but existing ones were already public, so they
+    // are published.
+    if missing_column(pool, "quizzes", "status").await {
+        sqlx::query("ALTER TABLE quizzes ADD COLUMN status TEXT NOT NULL DEFAULT 'draft'")
+            .execute(pool)
+            .await?;
+        sqlx::query("UPDATE quizzes SET status = 'published'")
+            .execute(pool)
+            .await?;
+    }
+
+    // Add updated_at column to quizzes if missing. SQLite can't ALTER in a
+    // non-constant default, so existing rows start out at their created_at.
+    if missing_column(pool, "quizzes", "updated_at").await {
+        sqlx::query("ALTER TABLE quizzes ADD COLUMN updated_at TEXT")
+            .execute(pool)
+            .await?;
+        sqlx::query("UPDATE quizzes SET updated_at = created_at WHERE updated_at IS NULL")
+            .execute(pool)
+            .await?;
+    }
+
     Ok(())
 }
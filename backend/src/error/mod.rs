@@ -0,0 +1,98 @@
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use serde_json::{json, Value as JsonValue};
+use std::fmt;
+
+/// Error returned by route handlers, rendered as the standard
+/// `{ "error": ..., "code": ... }` envelope with the matching status.
+///
+/// Every client error is built with its `code`, a stable snake_case name of
+/// the error that doesn't depend on the wording; the `localize_errors`
+/// middleware translates the message from it (see `i18n::localize`).
+/// `BadRequest` and `Conflict` carry the whole JSON body, `code` included,
+/// so they can add fields next to `error` (such as `question_index` or
+/// `current_version`); `bad_request` builds the plain form. `Internal` only
+/// carries the message sent to the client and always has the code
+/// `internal_error`; build it with `internal_from` where there is an
+/// underlying error, so that is logged.
+#[derive(Debug)]
+pub enum ApiError {
+    /// 400: the request is invalid
+    BadRequest(JsonValue),
+    /// 403: the caller may not change this (e.g. wrong owner token); the
+    /// code and the message
+    Forbidden(&'static str, String),
+    /// 404: the named thing doesn't exist (e.g. `quiz_not_found`, "Quiz not
+    /// found"); the code and the message
+    NotFound(&'static str, String),
+    /// 409: the request conflicts with the stored state
+    Conflict(JsonValue),
+    /// 500: reading or writing the database failed
+    Internal(String),
+}
+
+impl ApiError {
+    pub fn bad_request(code: &'static str, message: impl Into<String>) -> Self {
+        ApiError::BadRequest(json!({ "error": message.into(), "code": code }))
+    }
+
+    pub fn forbidden(code: &'static str, message: impl Into<String>) -> Self {
+        ApiError::Forbidden(code, message.into())
+    }
+
+    pub fn not_found(code: &'static str, message: impl Into<String>) -> Self {
+        ApiError::NotFound(code, message.into())
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        ApiError::Internal(message.into())
+    }
+
+    /// A 500 for `message` that logs `source`, the error behind it. The
+    /// client only sees `message`.
+    pub fn internal_from(message: impl Into<String>, source: impl fmt::Display) -> Self {
+        ApiError::Internal(log_error(message.into(), source))
+    }
+
+    fn body(&self) -> JsonValue {
+        match self {
+            ApiError::BadRequest(body) | ApiError::Conflict(body) => body.clone(),
+            ApiError::Forbidden(code, message) | ApiError::NotFound(code, message) => {
+                json!({ "error": message, "code": code })
+            }
+            ApiError::Internal(message) => json!({ "error": message, "code": "internal_error" }),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.body().get("error").and_then(JsonValue::as_str) {
+            Some(message) => f.write_str(message),
+            None => write!(f, "{}", self.status_code()),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Forbidden(..) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(..) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(self.body())
+    }
+}
+
+/// Log `source` as the cause of `message` and return `message`, for helpers
+/// that report a failure only by what failed
+pub fn log_error<M: fmt::Display>(message: M, source: impl fmt::Display) -> M {
+    log::error!("{}: {}", message, source);
+    message
+}
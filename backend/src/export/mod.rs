@@ -0,0 +1,262 @@
+use crate::models::{QuestionType, Quiz};
+use actix_web::http::header::{
+    Charset, ContentDisposition, DispositionParam, DispositionType, ExtendedValue,
+};
+
+/// Render a Markdown quiz description (CommonMark plus tables and
+/// strikethrough) to HTML, sanitized with ammonia so only safe tags and
+/// attributes survive: raw `<script>`, event handlers and `javascript:` links
+/// are stripped.
+pub fn markdown_to_html(markdown: &str) -> String {
+    let options =
+        pulldown_cmark::Options::ENABLE_TABLES | pulldown_cmark::Options::ENABLE_STRIKETHROUGH;
+    let mut html = String::with_capacity(markdown.len() * 3 / 2);
+    pulldown_cmark::html::push_html(
+        &mut html,
+        pulldown_cmark::Parser::new_ext(markdown, options),
+    );
+    ammonia::clean(&html)
+}
+
+/// Quote a CSV field when it contains a separator, quote or line break (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Render a quiz as CSV: `question_text, option_1..option_n, correct_answer`.
+///
+/// `n` is the largest option count in the quiz; questions with fewer options
+/// get empty trailing option cells. `correct_answer` is 1-based so it lines up
+/// with the `option_N` column names; multiple-answer questions list every
+/// correct option separated by `;`, and text questions their accepted answers
+/// the same way.
+pub fn quiz_to_csv(quiz: &Quiz) -> String {
+    let width = quiz
+        .questions
+        .iter()
+        .map(|q| q.options.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut header = vec!["question_text".to_string()];
+    header.extend((1..=width).map(|i| format!("option_{}", i)));
+    header.push("correct_answer".to_string());
+
+    let mut out = header.join(",");
+    out.push_str("\r\n");
+
+    for question in &quiz.questions {
+        let mut row = vec![csv_field(&question.text)];
+        row.extend(
+            (0..width).map(|i| csv_field(question.options.get(i).map_or("", String::as_str))),
+        );
+        let correct: Vec<String> = match question.question_type {
+            QuestionType::Text | QuestionType::Reading => {
+                question.accepted_answers().cloned().collect()
+            }
+            _ => question
+                .correct_answers
+                .iter()
+                .map(|i| (i + 1).to_string())
+                .collect(),
+        };
+        row.push(csv_field(&correct.join(";")));
+        out.push_str(&row.join(","));
+        out.push_str("\r\n");
+    }
+
+    out
+}
+
+/// Make a value safe for an Anki TSV field. Fields are imported as HTML, so
+/// line breaks become `<br>`; tabs would split the field and become spaces.
+fn anki_field(value: &str) -> String {
+    value
+        .replace("\r\n", "<br>")
+        .replace(['\n', '\r'], "<br>")
+        .replace('\t', " ")
+}
+
+/// Render a quiz as an Anki-importable TSV: `front<TAB>back` per question.
+///
+/// The front is the question text; the back is the correct option(s) (or the
+/// accepted answers of a text question) followed by the reading, if any. The
+/// `#separator`/`#html` header lines tell Anki how to read the file.
+pub fn quiz_to_anki(quiz: &Quiz) -> String {
+    let mut out = String::from("#separator:tab\n#html:true\n");
+
+    for question in &quiz.questions {
+        let answers: Vec<&str> = match question.question_type {
+            QuestionType::Text | QuestionType::Reading => {
+                question.accepted_answers().map(String::as_str).collect()
+            }
+            _ => question
+                .correct_answers
+                .iter()
+                .filter_map(|&i| usize::try_from(i).ok())
+                .filter_map(|i| question.options.get(i))
+                .map(String::as_str)
+                .collect(),
+        };
+
+        let mut back = anki_field(&answers.join(", "));
+        if let Some(reading) = question
+            .reading
+            .as_deref()
+            .filter(|r| !r.is_empty() && question.question_type != QuestionType::Reading)
+        {
+            back.push_str("<br>");
+            back.push_str(&anki_field(reading));
+        }
+
+        out.push_str(&anki_field(&question.text));
+        out.push('\t');
+        out.push_str(&back);
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Escape text for use in HTML element content and attribute values
+pub fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Option label used on printouts: A-Z, then AA, AB, ...
+fn option_letter(index: usize) -> String {
+    let mut n = index + 1;
+    let mut letters = Vec::new();
+    while n > 0 {
+        n -= 1;
+        letters.push(char::from(b'A' + (n % 26) as u8));
+        n /= 26;
+    }
+    letters.iter().rev().collect()
+}
+
+/// Styles for `quiz_to_html`; the answer key starts on a new page when printed
+const PRINT_STYLE: &str =
+    "body { font-family: sans-serif; max-width: 48em; margin: 2em auto; line-height: 1.6; }
+.questions > li { margin-bottom: 1.2em; break-inside: avoid; }
+.options { list-style-type: upper-latin; }
+.blank { display: inline-block; min-width: 12em; border-bottom: 1px solid; }
+rt { font-size: 0.6em; }
+.answer-key { break-before: page; }";
+
+/// Render a quiz as a standalone printable HTML handout.
+///
+/// Questions are numbered and their options lettered (A, B, ...); text
+/// questions get a blank line to write on. Readings are shown as furigana
+/// (`<ruby>`) over the question text, and the answers follow in a separate
+/// answer key section. All quiz content is HTML-escaped.
+pub fn quiz_to_html(quiz: &Quiz) -> String {
+    let title = html_escape(&quiz.title);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html lang=\"ja\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n<h1>{}</h1>\n",
+        title, PRINT_STYLE, title
+    );
+    if let Some(description) = quiz.description.as_deref().filter(|d| !d.is_empty()) {
+        out.push_str(&format!("<p>{}</p>\n", html_escape(description)));
+    }
+
+    out.push_str("<ol class=\"questions\">\n");
+    for question in &quiz.questions {
+        let text = html_escape(&question.text);
+        // A reading question's reading is its answer, so it stays off the sheet
+        let prompt = match question
+            .reading
+            .as_deref()
+            .filter(|r| !r.is_empty() && question.question_type != QuestionType::Reading)
+        {
+            Some(reading) => format!("<ruby>{}<rt>{}</rt></ruby>", text, html_escape(reading)),
+            None => text,
+        };
+        out.push_str(&format!("<li>\n<p>{}</p>\n", prompt));
+        if matches!(
+            question.question_type,
+            QuestionType::Text | QuestionType::Reading
+        ) {
+            out.push_str("<p><span class=\"blank\"></span></p>\n");
+        } else {
+            out.push_str("<ol class=\"options\">\n");
+            for option in &question.options {
+                out.push_str(&format!("<li>{}</li>\n", html_escape(option)));
+            }
+            out.push_str("</ol>\n");
+        }
+        out.push_str("</li>\n");
+    }
+    out.push_str("</ol>\n");
+
+    out.push_str("<section class=\"answer-key\">\n<h2>Answer key</h2>\n<ol>\n");
+    for question in &quiz.questions {
+        let answer = match question.question_type {
+            QuestionType::Text | QuestionType::Reading => question
+                .accepted_answers()
+                .map(|a| html_escape(a))
+                .collect::<Vec<_>>()
+                .join(" / "),
+            _ => question
+                .correct_answers
+                .iter()
+                .filter_map(|&i| usize::try_from(i).ok())
+                .filter(|&i| i < question.options.len())
+                .map(|i| {
+                    format!(
+                        "{} ({})",
+                        option_letter(i),
+                        html_escape(&question.options[i])
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+        out.push_str(&format!("<li>{}</li>\n", answer));
+    }
+    out.push_str("</ol>\n</section>\n</body>\n</html>\n");
+
+    out
+}
+
+/// `Content-Disposition: attachment` named after the quiz title.
+///
+/// Titles are mostly Japanese, so the UTF-8 name goes in `filename*` and an
+/// ASCII-only fallback (`quiz-<id>` when nothing ASCII is left) in `filename`.
+pub fn attachment(quiz: &Quiz, extension: &str) -> ContentDisposition {
+    let title = quiz.title.trim();
+
+    let mut ascii: String = title
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    ascii = ascii.trim_matches('_').to_string();
+    if ascii.is_empty() {
+        ascii = format!("quiz-{}", quiz.id);
+    }
+
+    let mut parameters = vec![DispositionParam::Filename(format!(
+        "{}.{}",
+        ascii, extension
+    ))];
+    if !title.is_empty() {
+        parameters.push(DispositionParam::FilenameExt(ExtendedValue {
+            charset: Charset::Ext("UTF-8".to_string()),
+            language_tag: None,
+            value: format!("{}.{}", title, extension).into_bytes(),
+        }));
+    }
+
+    ContentDisposition {
+        disposition: DispositionType::Attachment,
+        parameters,
+    }
+}
@@ -0,0 +1,279 @@
+use actix_web::http::header::{AcceptLanguage, Preference, Quality};
+use actix_web::http::StatusCode;
+use serde_json::{Map, Value as JsonValue};
+
+/// Languages error messages are available in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    En,
+    Ja,
+}
+
+impl Lang {
+    /// The `Content-Language` tag of the language
+    pub fn tag(self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::Ja => "ja",
+        }
+    }
+
+    /// The most preferred supported language of an `Accept-Language` header
+    /// (`ja-JP` counts as `ja`), or English when none is acceptable
+    pub fn from_accept_language(header: &AcceptLanguage) -> Lang {
+        let mut ranked: Vec<_> = header
+            .0
+            .iter()
+            .filter(|l| l.quality > Quality::ZERO)
+            .collect();
+        // stable, so languages of equal weight keep their listed order
+        ranked.sort_by_key(|l| std::cmp::Reverse(l.quality));
+        ranked
+            .into_iter()
+            .find_map(|l| match &l.item {
+                Preference::Specific(tag) => match tag.primary_language() {
+                    "en" => Some(Lang::En),
+                    "ja" => Some(Lang::Ja),
+                    _ => None,
+                },
+                Preference::Any => None,
+            })
+            .unwrap_or(Lang::En)
+    }
+}
+
+/// An error with a fixed message: its `code` and its Japanese text
+struct Message {
+    code: &'static str,
+    ja: &'static str,
+}
+
+/// Japanese text of the errors whose message never varies, by the `code`
+/// they are built with (see `ApiError`). Errors whose message names a value,
+/// such as a field, a limit or an index, aren't listed.
+const MESSAGES: &[Message] = &[
+    Message {
+        code: "quiz_not_found",
+        ja: "クイズが見つかりません",
+    },
+    Message {
+        code: "deleted_quiz_not_found",
+        ja: "削除済みのクイズが見つかりません",
+    },
+    Message {
+        code: "question_not_found",
+        ja: "問題が見つかりません",
+    },
+    Message {
+        code: "test_not_found",
+        ja: "テストが見つかりません",
+    },
+    Message {
+        code: "not_found",
+        ja: "見つかりません",
+    },
+    Message {
+        code: "invalid_quiz",
+        ja: "クイズの内容に誤りがあります",
+    },
+    Message {
+        code: "title_required",
+        ja: "タイトルを入力してください",
+    },
+    Message {
+        code: "questions_required",
+        ja: "クイズには問題が1つ以上必要です",
+    },
+    Message {
+        code: "no_questions_given",
+        ja: "問題が指定されていません",
+    },
+    Message {
+        code: "invalid_csv",
+        ja: "CSVの形式に誤りがあります",
+    },
+    Message {
+        code: "duplicate_title",
+        ja: "同じタイトルのクイズがすでに存在します",
+    },
+    Message {
+        code: "version_conflict",
+        ja: "読み込んだ後にクイズが変更されています",
+    },
+    Message {
+        code: "source_quizzes_missing",
+        ja: "存在しない統合元のクイズがあります",
+    },
+    Message {
+        code: "source_quiz_deleted",
+        ja: "統合中に統合元のクイズが削除されました",
+    },
+    Message {
+        code: "not_bank_questions",
+        ja: "追加できるのは問題バンクの問題だけです",
+    },
+    Message {
+        code: "already_attached",
+        ja: "すでにクイズに含まれている問題があります",
+    },
+    Message {
+        code: "not_attached",
+        ja: "外せるのはクイズに追加された問題バンクの問題だけです",
+    },
+    Message {
+        code: "owner_token_required",
+        ja: "このクイズを変更するには X-Owner-Token が必要です",
+    },
+    Message {
+        code: "owner_token_mismatch",
+        ja: "X-Owner-Token がクイズと一致しません",
+    },
+    Message {
+        code: "reading_required",
+        ja: "読みの問題には reading が必要です",
+    },
+    Message {
+        code: "options_not_allowed",
+        ja: "読みの問題には選択肢を指定できません",
+    },
+    Message {
+        code: "answer_text_required",
+        ja: "answer_text には正解を1つ以上指定してください",
+    },
+    Message {
+        code: "correct_answer_required",
+        ja: "correct_answer を指定してください",
+    },
+    Message {
+        code: "correct_answers_required",
+        ja: "複数選択の問題では correct_answers に選択肢を1つ以上指定してください",
+    },
+    Message {
+        code: "example_sentence_required",
+        ja: "example_reading には example_sentence が必要です",
+    },
+    Message {
+        code: "version_required",
+        ja: "version を指定してください",
+    },
+    Message {
+        code: "nothing_to_update",
+        ja: "更新する項目がありません。title、description、jlpt_level または status を指定してください",
+    },
+    Message {
+        code: "no_ids_given",
+        ja: "ids にはクイズを1つ以上指定してください",
+    },
+    Message {
+        code: "too_few_sources",
+        ja: "source_ids にはクイズを2つ以上指定してください",
+    },
+    Message {
+        code: "invalid_order",
+        ja: "order にはクイズのすべての問題の ID を1回ずつ指定してください",
+    },
+    Message {
+        code: "no_questions_matched",
+        ja: "条件に合う問題がありません",
+    },
+    Message {
+        code: "q_required",
+        ja: "q を指定してください",
+    },
+    Message {
+        code: "session_required",
+        ja: "session を指定してください",
+    },
+    Message {
+        code: "unreadable_body",
+        ja: "リクエストの本文を読み込めませんでした",
+    },
+    Message {
+        code: "csv_text_column_missing",
+        ja: "ヘッダーに text 列がありません",
+    },
+    Message {
+        code: "csv_correct_column_missing",
+        ja: "ヘッダーに correct 列がありません",
+    },
+    Message {
+        code: "csv_option_columns_missing",
+        ja: "ヘッダーには選択肢の列（option1、option2 など）が2つ以上必要です",
+    },
+    Message {
+        code: "rate_limited",
+        ja: "リクエストが多すぎます。しばらくしてからもう一度お試しください",
+    },
+    Message {
+        code: "request_timeout",
+        ja: "リクエストがタイムアウトしました",
+    },
+];
+
+/// Code, and Japanese text, of an error whose message isn't in `MESSAGES`
+fn status_fallback(status: StatusCode) -> (&'static str, &'static str) {
+    match status {
+        StatusCode::BAD_REQUEST => ("bad_request", "リクエストに誤りがあります"),
+        StatusCode::FORBIDDEN => ("forbidden", "この操作は許可されていません"),
+        StatusCode::NOT_FOUND => ("not_found", "見つかりません"),
+        StatusCode::CONFLICT => ("conflict", "現在の状態と矛盾するリクエストです"),
+        StatusCode::PAYLOAD_TOO_LARGE => ("payload_too_large", "リクエストが大きすぎます"),
+        StatusCode::TOO_MANY_REQUESTS => ("rate_limited", "リクエストが多すぎます"),
+        StatusCode::GATEWAY_TIMEOUT => ("request_timeout", "リクエストがタイムアウトしました"),
+        s if s.is_client_error() => ("client_error", "リクエストに誤りがあります"),
+        _ => ("internal_error", "サーバーでエラーが発生しました"),
+    }
+}
+
+/// Japanese text of the error with `code`, when its message never varies
+fn japanese(code: Option<&JsonValue>) -> Option<&'static str> {
+    let code = code.and_then(JsonValue::as_str)?;
+    MESSAGES
+        .iter()
+        .find(|known| known.code == code)
+        .map(|known| known.ja)
+}
+
+/// Give a JSON error body a machine-readable `code` (unless it has one) and
+/// put its `error` message in `lang`.
+///
+/// The message is translated from the body's `code`, which `ApiError` and
+/// the middleware add when building the error, so the wording of the
+/// English text doesn't matter. Codes in `MESSAGES` get their translation.
+/// A body without a code gets one for its status. Otherwise, in Japanese a
+/// server error's message is replaced by a generic one, while a client
+/// error keeps its English text, which names the offending field or value.
+/// Entries of an `errors` list (see `FieldError` and `RowError`) are
+/// translated by their own `code` in the same way.
+pub fn localize(body: &mut Map<String, JsonValue>, status: StatusCode, lang: Lang) {
+    if lang == Lang::Ja {
+        if let Some(JsonValue::Array(errors)) = body.get_mut("errors") {
+            for entry in errors.iter_mut().filter_map(JsonValue::as_object_mut) {
+                let Some(translated) = japanese(entry.get("code")) else {
+                    continue;
+                };
+                let key = if entry.contains_key("message") {
+                    "message"
+                } else {
+                    "error"
+                };
+                entry.insert(key.to_string(), translated.into());
+            }
+        }
+    }
+
+    let (fallback_code, fallback_ja) = status_fallback(status);
+    let code = body
+        .entry("code")
+        .or_insert_with(|| fallback_code.into())
+        .clone();
+    if lang == Lang::Ja {
+        let translated = match japanese(Some(&code)) {
+            Some(translated) => Some(translated),
+            None => status.is_server_error().then_some(fallback_ja),
+        };
+        if let Some(translated) = translated {
+            body.insert("error".to_string(), translated.into());
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use crate::models::{QuestionInput, QuestionType};
+use serde::Serialize;
+
+/// A problem with one line of an imported file
+#[derive(Debug, Serialize)]
+pub struct RowError {
+    /// 1-based line number in the file
+    pub line: u64,
+    /// Error code, as in the response envelope
+    pub code: &'static str,
+    pub error: String,
+}
+
+/// The code and message of a problem with a CSV file
+type Problem = (&'static str, String);
+
+/// Column positions found in a CSV header row
+struct CsvColumns {
+    text: usize,
+    options: Vec<usize>,
+    correct: usize,
+}
+
+impl CsvColumns {
+    /// Locate the columns by name (case-insensitive): `text` (or
+    /// `question_text`), any number of `option…` columns in file order, and
+    /// `correct` (or `correct_answer`). The names `quiz_to_csv` writes are
+    /// accepted, so an export can be imported again.
+    fn from_header(header: &csv::StringRecord) -> Result<Self, Problem> {
+        let mut text = None;
+        let mut correct = None;
+        let mut options = Vec::new();
+        for (index, name) in header.iter().enumerate() {
+            let name = name.trim().to_lowercase();
+            match name.as_str() {
+                "text" | "question_text" => text = Some(index),
+                "correct" | "correct_answer" => correct = Some(index),
+                _ if name.starts_with("option") => options.push(index),
+                _ => {}
+            }
+        }
+
+        let text = text.ok_or((
+            "csv_text_column_missing",
+            "header has no text column".into(),
+        ))?;
+        let correct = correct.ok_or((
+            "csv_correct_column_missing",
+            "header has no correct column".into(),
+        ))?;
+        if options.len() < 2 {
+            return Err((
+                "csv_option_columns_missing",
+                "header needs at least two option columns (option1, option2, ...)".into(),
+            ));
+        }
+        Ok(CsvColumns {
+            text,
+            options,
+            correct,
+        })
+    }
+
+    /// Build and validate the question on one data row
+    fn question(&self, record: &csv::StringRecord) -> Result<QuestionInput, Problem> {
+        let field = |index: usize| record.get(index).unwrap_or("").trim();
+
+        // Trailing empty option cells are padding for questions with fewer
+        // options; empty cells before a filled one are reported by validate()
+        let mut options: Vec<String> = self.options.iter().map(|&i| field(i).to_string()).collect();
+        while options.last().is_some_and(|o| o.is_empty()) {
+            options.pop();
+        }
+
+        let mut correct = Vec::new();
+        for value in field(self.correct)
+            .split(';')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+        {
+            match value.parse::<i32>() {
+                Ok(n) if n as usize > options.len() => {
+                    return Err((
+                        "answer_out_of_range",
+                        format!(
+                            "correct {} is out of range for {} options",
+                            n,
+                            options.len()
+                        ),
+                    ))
+                }
+                Ok(n) if n >= 1 => correct.push(n - 1),
+                _ => {
+                    return Err((
+                        "invalid_option_number",
+                        format!(
+                            "correct '{}' is not an option number (1 for the first option)",
+                            value
+                        ),
+                    ))
+                }
+            }
+        }
+
+        let mut question = QuestionInput {
+            text: field(self.text).to_string(),
+            options,
+            question_type: if correct.len() > 1 {
+                QuestionType::Multiple
+            } else {
+                QuestionType::Single
+            },
+            correct_answer: correct.first().copied(),
+            correct_answers: correct,
+            answer_text: None,
+            exact_script: false,
+            reading: None,
+            audio_url: None,
+            explanation: None,
+            example_sentence: None,
+            example_reading: None,
+            difficulty: None,
+        };
+        question.normalize();
+        question
+            .validate()
+            .map_err(|problem| (problem.code, problem.message))?;
+        Ok(question)
+    }
+}
+
+/// Parse a CSV question list: a header row, then one question per row with
+/// its text, options and the 1-based number of the correct option (several
+/// numbers separated by `;` make a multiple-answer question).
+///
+/// Every row is checked; if any fails, all the failures are returned with
+/// their line numbers.
+pub fn questions_from_csv(body: &str) -> Result<Vec<QuestionInput>, Vec<RowError>> {
+    let body = body.strip_prefix('\u{feff}').unwrap_or(body);
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(body.as_bytes());
+
+    let columns = reader
+        .headers()
+        .map_err(|e| ("invalid_csv", e.to_string()))
+        .and_then(CsvColumns::from_header)
+        .map_err(|(code, error)| {
+            vec![RowError {
+                line: 1,
+                code,
+                error,
+            }]
+        })?;
+
+    let mut questions = Vec::new();
+    let mut errors = Vec::new();
+    for record in reader.records() {
+        let (line, result) = match record {
+            Ok(record) => (
+                record.position().map_or(0, |p| p.line()),
+                columns.question(&record),
+            ),
+            Err(e) => (
+                e.position().map_or(0, |p| p.line()),
+                Err(("invalid_csv", e.to_string())),
+            ),
+        };
+        match result {
+            Ok(question) => questions.push(question),
+            Err((code, error)) => errors.push(RowError { line, code, error }),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(questions)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Splits an NDJSON body that arrives in chunks into its lines, holding at
+/// most one line of up to `max_line_bytes` in memory. Blank lines are
+/// skipped.
+pub struct NdjsonLines {
+    max_line_bytes: usize,
+    line: Vec<u8>,
+    /// Line number of the line being read, 1-based
+    number: u64,
+    /// Set while the rest of an over-long line is being dropped
+    too_long: bool,
+}
+
+impl NdjsonLines {
+    pub fn new(max_line_bytes: usize) -> Self {
+        NdjsonLines {
+            max_line_bytes,
+            line: Vec::new(),
+            number: 1,
+            too_long: false,
+        }
+    }
+
+    /// Feed the next chunk of the body; returns the lines it completed, each
+    /// with its line number, as the line's bytes or an error for a line over
+    /// the limit
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<(u64, Result<Vec<u8>, String>)> {
+        let mut lines = Vec::new();
+        for part in chunk.split_inclusive(|b| *b == b'\n') {
+            let (part, ends_line) = match part.strip_suffix(b"\n") {
+                Some(part) => (part, true),
+                None => (part, false),
+            };
+            if self.line.len() + part.len() > self.max_line_bytes {
+                self.line.clear();
+                self.too_long = true;
+            } else if !self.too_long {
+                self.line.extend_from_slice(part);
+            }
+            if ends_line {
+                lines.extend(self.end_line());
+            }
+        }
+        lines
+    }
+
+    /// The last line, when the body doesn't end with a newline
+    pub fn finish(mut self) -> Option<(u64, Result<Vec<u8>, String>)> {
+        self.end_line()
+    }
+
+    fn end_line(&mut self) -> Option<(u64, Result<Vec<u8>, String>)> {
+        let number = self.number;
+        self.number += 1;
+        let line = std::mem::take(&mut self.line);
+        if std::mem::take(&mut self.too_long) {
+            let error = format!("Line is longer than {} bytes", self.max_line_bytes);
+            return Some((number, Err(error)));
+        }
+        if line.iter().all(u8::is_ascii_whitespace) {
+            return None;
+        }
+        Some((number, Ok(line)))
+    }
+}
@@ -1,55 +1,291 @@
+mod cache;
+mod config;
 mod db;
+mod error;
+mod export;
+mod i18n;
+mod import;
+mod middleware;
 mod models;
+mod openapi;
+mod purge;
+mod queries;
+mod readings;
 mod routes;
+mod seed;
+mod srs;
+mod tools;
+
+#[cfg(test)]
+mod tests;
 
 use actix_cors::Cors;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::middleware::{from_fn, Compress, Logger};
 use actix_web::{web, App, HttpServer};
-/* SqlitePool is Clone and can be cloned directly when passed into actix-web App */
 use sqlx::SqlitePool;
 
+/// Access log line: request id, client address, method + path, status,
+/// response size and duration in milliseconds
+const ACCESS_LOG_FORMAT: &str = r#"%{x-request-id}o %a "%r" %s %b %Dms"#;
+
+/// Build the CORS middleware.
+///
+/// With an allowlist (from `ALLOWED_ORIGINS`) only those exact origins may make
+/// cross-origin requests. Without one every origin is allowed, which keeps local
+/// development working out of the box but must not be used in deployments.
+fn build_cors(allowed_origins: Option<&[String]>) -> Cors {
+    let cors = match allowed_origins {
+        Some(origins) => origins
+            .iter()
+            .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin)),
+        None => Cors::default().allow_any_origin(),
+    };
+
+    cors.allow_any_method().allow_any_header().max_age(3600)
+}
+
+/// Everything the workers' `App`s share, created once in `main`
+#[derive(Clone)]
+struct AppState {
+    pool: SqlitePool,
+    config: web::Data<config::Config>,
+    rate_limiter: web::Data<middleware::RateLimiter>,
+    metrics: web::Data<middleware::Metrics>,
+    question_counts: web::Data<cache::QuestionCounts>,
+    kanji_readings: web::Data<readings::KanjiReadings>,
+    db_health: web::Data<db::DbHealth>,
+}
+
+/// The application as served: middleware, shared state and routes. Each
+/// worker builds its own; the tests in `tests` build it the same way.
+fn build_app(
+    state: &AppState,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    let max_body_bytes = state.config.max_body_bytes;
+    let cors = build_cors(state.config.allowed_origins.as_deref());
+
+    App::new()
+        // innermost, so only the extractors and handler count against
+        // the timeout and its 504 still gets CORS headers and a request id
+        .wrap(from_fn(middleware::request_timeout))
+        // inside CORS so 429 responses still carry the CORS headers
+        .wrap(from_fn(middleware::rate_limit))
+        .wrap(cors)
+        // inside request_id, which adds its field to the localized body
+        .wrap(from_fn(middleware::localize_errors))
+        .wrap(from_fn(middleware::request_id))
+        .wrap(from_fn(middleware::record_metrics))
+        // gzip/brotli/zstd per Accept-Encoding; outside request_id, which
+        // needs the uncompressed error bodies
+        .wrap(Compress::default())
+        // outermost, so it sees the final status and the X-Request-Id header
+        .wrap(Logger::new(ACCESS_LOG_FORMAT))
+        // provide the shared SQLite pool to handlers
+        .app_data(web::Data::new(state.pool.clone()))
+        .app_data(state.rate_limiter.clone())
+        .app_data(state.metrics.clone())
+        .app_data(state.question_counts.clone())
+        .app_data(state.config.clone())
+        .app_data(state.kanji_readings.clone())
+        .app_data(state.db_health.clone())
+        // cap request bodies (oversized ones get a 413) and report malformed
+        // bodies with the standard JSON error envelope
+        .app_data(
+            web::JsonConfig::default()
+                .limit(max_body_bytes)
+                .error_handler(routes::json_error_handler),
+        )
+        .app_data(web::PayloadConfig::new(max_body_bytes))
+        // configure routes (includes quizzes and tests)
+        .configure(routes::config)
+}
+
+/// Seconds in-flight requests get to finish once a shutdown signal arrives
+const SHUTDOWN_TIMEOUT_SECS: u64 = 30;
+
+/// Resolve when the process receives SIGINT (Ctrl+C) or, on Unix, SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            log::error!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sig) => {
+                sig.recv().await;
+            }
+            Err(e) => {
+                log::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => log::info!("Received SIGINT, shutting down"),
+        _ = terminate => log::info!("Received SIGTERM, shutting down"),
+    }
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv::dotenv().ok();
+    env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
 
-    // Get SQLite database URL from environment variable
-    let database_url = std::env::var("DATABASE_URL")
-        .unwrap_or_else(|_| "sqlite://mimikara_n3_questions.db".to_string());
+    // `seed` fills an empty database with sample quizzes instead of serving
+    let seed_only = match std::env::args().nth(1).as_deref() {
+        None => false,
+        Some("seed") => true,
+        Some(other) => {
+            log::error!("Unknown command '{}'; the only command is 'seed'", other);
+            std::process::exit(2);
+        }
+    };
 
-    println!("Connecting to SQLite database...");
+    let config = match config::Config::from_env() {
+        Ok(config) => config,
+        Err(msg) => {
+            log::error!("{}", msg);
+            std::process::exit(1);
+        }
+    };
+
+    log::info!("Connecting to SQLite database...");
 
     // Create a connection pool to the SQLite database
-    let pool = SqlitePool::connect(&database_url)
-        .await
-        .expect("Failed to connect to SQLite database");
+    let pool = match db::connect(&config.database).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            log::error!("Failed to connect to SQLite database: {}", e);
+            std::process::exit(1);
+        }
+    };
 
-    println!("Connected to SQLite database successfully");
+    log::info!("Connected to SQLite database successfully");
     
-    db::init_db(&pool)
-        .await
-        .expect("Failed to initialize database");
+    if let Err(e) = db::init_db(&pool).await {
+        log::error!("Failed to initialize database: {}", e);
+        std::process::exit(1);
+    }
+
+    log::info!("Database initialized successfully");
+
+    if seed_only {
+        match seed::run(&pool).await {
+            Ok(seeded) if seeded.is_empty() => log::info!("Database already has quizzes; nothing seeded"),
+            Ok(seeded) => {
+                log::info!("Seeded {} sample quizzes", seeded.len());
+                // only their hashes are stored, so this is the one chance to note them
+                for (id, owner_token) in &seeded {
+                    println!("quiz {} owner token: {}", id, owner_token);
+                }
+            }
+            Err(msg) => {
+                log::error!("{}", msg);
+                std::process::exit(1);
+            }
+        }
+        pool.close().await;
+        return Ok(());
+    }
+
+    // Checked before the server starts so a bad path or file stops it
+    let kanji_readings = match &config.kanji_readings_path {
+        Some(path) => match readings::KanjiReadings::load(path) {
+            Ok(readings) => {
+                log::info!(
+                    "Loaded readings of {} kanji from {}",
+                    readings.kanji_count(),
+                    path.display()
+                );
+                readings
+            }
+            Err(e) => {
+                log::error!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        None => readings::KanjiReadings::default(),
+    };
+    let kanji_readings = web::Data::new(kanji_readings);
+
+    let db_health = web::Data::new(db::DbHealth::new());
+    db::spawn_monitor(
+        pool.clone(),
+        db_health.clone().into_inner(),
+        config.database.health_check_interval,
+    );
 
-    println!("Database initialized successfully");
+    match config.purge_interval {
+        Some(interval) => purge::spawn(pool.clone(), interval, config.purge_retention_days),
+        None => log::warn!("PURGE_INTERVAL_SECS is 0; deleted quizzes are never purged"),
+    }
 
     // SqlitePool is cloneable and safe to share between threads (SqlitePool implements Clone)
     // No Arc wrapper is needed; we'll clone the pool directly when providing it to App.
 
-    HttpServer::new(move || {
-        let cors = Cors::default()
-            .allow_any_origin()
-            .allow_any_method()
-            .allow_any_header()
-            .max_age(3600);
-
-        App::new()
-            .wrap(cors)
-            // provide the shared SQLite pool to handlers
-            .app_data(web::Data::new(pool.clone()))
-            // allow larger JSON payloads for test creation (adjust limit as needed)
-            .app_data(web::JsonConfig::default().limit(10 * 1024 * 1024))
-            // configure routes (includes quizzes and tests)
-            .configure(routes::config)
-    })
-    .bind(("127.0.0.1", 8081))?
-    .run()
-    .await
+    match &config.allowed_origins {
+        Some(origins) => log::info!("CORS restricted to: {}", origins.join(", ")),
+        None => log::warn!("ALLOWED_ORIGINS is not set; CORS allows any origin"),
+    }
+
+    if config.rate_limit_per_minute == 0 {
+        log::warn!("RATE_LIMIT_PER_MINUTE is 0; write requests are not rate limited");
+    }
+    // Shared by all workers so the limit applies per process, not per worker
+    let rate_limiter =
+        web::Data::new(middleware::RateLimiter::new(config.rate_limit_per_minute));
+    let metrics = web::Data::new(middleware::Metrics::new());
+    let question_counts = web::Data::new(cache::QuestionCounts::new());
+    let (bind_address, port) = (config.bind_address, config.port);
+
+    let state = AppState {
+        pool: pool.clone(),
+        config: web::Data::new(config),
+        rate_limiter,
+        metrics,
+        question_counts,
+        kanji_readings,
+        db_health,
+    };
+    let server = HttpServer::new(move || build_app(&state))
+    .bind((bind_address, port))?
+    // signals are handled below so shutdown is logged and the pool closed afterwards
+    .disable_signals()
+    .shutdown_timeout(SHUTDOWN_TIMEOUT_SECS)
+    .run();
+
+    let handle = server.handle();
+    actix_web::rt::spawn(async move {
+        shutdown_signal().await;
+        log::info!(
+            "Stopping server, waiting up to {}s for in-flight requests",
+            SHUTDOWN_TIMEOUT_SECS
+        );
+        handle.stop(true).await;
+    });
+
+    let result = server.await;
+
+    pool.close().await;
+    log::info!("Server stopped, database pool closed");
+
+    result
 }
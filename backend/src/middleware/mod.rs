@@ -0,0 +1,399 @@
+use crate::config::Config;
+use crate::i18n::{self, Lang};
+use actix_web::body::{self, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::InternalError;
+use actix_web::http::header::{self, Header, HeaderMap, HeaderName, HeaderValue};
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::Next;
+use actix_web::web::Bytes;
+use actix_web::{web, Error, HttpResponse};
+use prometheus::{
+    core::Collector, Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use serde_json::{json, Map, Value as JsonValue};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Response header carrying the per-request id
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Assign every request a random UUID.
+///
+/// The id is echoed in the `X-Request-Id` response header (which the access
+/// log prints) and added as `request_id` to JSON error bodies, so users can
+/// quote it in bug reports. Errors returned by inner middleware (such as the
+/// 504 from `request_timeout`) are only rendered further out, so they get
+/// the same treatment through a response mapper.
+pub async fn request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let id = Uuid::new_v4();
+    let mut res = match next.call(req).await {
+        Ok(res) => res.map_into_boxed_body(),
+        Err(mut err) => {
+            err.add_response_mapper(move |res| tag_error_response(res, id));
+            return Err(err);
+        }
+    };
+
+    if let Ok(value) = HeaderValue::from_str(&id.to_string()) {
+        res.headers_mut()
+            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+
+    Ok(edit_error_body(res, move |body| tag_body(body, id)).await)
+}
+
+/// Add `request_id` to a JSON error body
+fn tag_body(body: &mut Map<String, JsonValue>, id: Uuid) {
+    body.insert("request_id".to_string(), JsonValue::from(id.to_string()));
+}
+
+/// `request_id` for a response rendered from an error: the header, plus the
+/// body field when the body is JSON that is already in memory
+fn tag_error_response(res: HttpResponse, id: Uuid) -> HttpResponse {
+    let mut res = edit_rendered_error_body(res, |body| tag_body(body, id));
+    if let Ok(value) = HeaderValue::from_str(&id.to_string()) {
+        res.headers_mut()
+            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+    res
+}
+
+/// Pick the language of error messages from `Accept-Language` (English by
+/// default), then give JSON error bodies a `code` and localize their
+/// `error` with `i18n::localize`. Localized responses say which language
+/// they are in with `Content-Language`.
+pub async fn localize_errors(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let lang = header::AcceptLanguage::parse(&req)
+        .map(|accept| Lang::from_accept_language(&accept))
+        .unwrap_or(Lang::En);
+    let res = match next.call(req).await {
+        Ok(res) => res.map_into_boxed_body(),
+        Err(mut err) => {
+            err.add_response_mapper(move |res| {
+                let status = res.status();
+                let res = edit_rendered_error_body(res, |body| i18n::localize(body, status, lang));
+                with_content_language(res, lang)
+            });
+            return Err(err);
+        }
+    };
+
+    let status = res.status();
+    if !is_json_error(res.headers(), status) {
+        return Ok(res);
+    }
+    let res = edit_error_body(res, move |body| i18n::localize(body, status, lang)).await;
+    let (req, res) = res.into_parts();
+    Ok(ServiceResponse::new(req, with_content_language(res, lang)))
+}
+
+fn with_content_language(mut res: HttpResponse, lang: Lang) -> HttpResponse {
+    res.headers_mut().insert(
+        header::CONTENT_LANGUAGE,
+        HeaderValue::from_static(lang.tag()),
+    );
+    res
+}
+
+/// Whether a response is an error with a JSON body
+fn is_json_error(headers: &HeaderMap, status: StatusCode) -> bool {
+    let is_json = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    is_json && (status.is_client_error() || status.is_server_error())
+}
+
+/// Apply `edit` to a JSON object body; anything else is left as-is
+fn edit_json_object(bytes: Bytes, edit: impl FnOnce(&mut Map<String, JsonValue>)) -> Bytes {
+    match serde_json::from_slice::<JsonValue>(&bytes) {
+        Ok(JsonValue::Object(mut map)) => {
+            edit(&mut map);
+            serde_json::to_vec(&map).map(Into::into).unwrap_or(bytes)
+        }
+        _ => bytes,
+    }
+}
+
+/// Apply `edit` to the body of a JSON error response; other responses are
+/// passed through
+async fn edit_error_body(
+    res: ServiceResponse<BoxBody>,
+    edit: impl FnOnce(&mut Map<String, JsonValue>),
+) -> ServiceResponse<BoxBody> {
+    if !is_json_error(res.headers(), res.status()) {
+        return res;
+    }
+
+    // Error bodies are small JSON envelopes, so buffering them is cheap.
+    let (req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let bytes = match body::to_bytes(body).await {
+        Ok(b) => b,
+        Err(_) => return ServiceResponse::new(req, res.set_body(BoxBody::new(()))),
+    };
+
+    let bytes = edit_json_object(bytes, edit);
+
+    ServiceResponse::new(req, res.set_body(BoxBody::new(bytes)))
+}
+
+/// Like `edit_error_body`, for a response rendered from an error, whose
+/// JSON body is only edited when it is already in memory
+fn edit_rendered_error_body(
+    res: HttpResponse,
+    edit: impl FnOnce(&mut Map<String, JsonValue>),
+) -> HttpResponse {
+    if !is_json_error(res.headers(), res.status()) {
+        return res;
+    }
+    let (res, body) = res.into_parts();
+    match body.try_into_bytes() {
+        Ok(bytes) => res.set_body(BoxBody::new(edit_json_object(bytes, edit))),
+        Err(body) => res.set_body(body),
+    }
+}
+
+/// Answer 504 when a request takes longer than `Config::request_timeout`.
+///
+/// The rest of the request (body extraction and the handler) is a future
+/// that is dropped on timeout, which cancels it at its next `.await`; any
+/// open transaction it held is rolled back when dropped.
+///
+/// The request can't be kept back to build the 504 from (routing further in
+/// needs it to be the only handle), so the 504 is returned as an error
+/// carrying the response, which the outer middleware render.
+pub async fn request_timeout(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let timeout = req
+        .app_data::<web::Data<Config>>()
+        .and_then(|config| config.request_timeout);
+    let Some(timeout) = timeout else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+
+    let (method, path) = (req.method().clone(), req.path().to_string());
+    match tokio::time::timeout(timeout, next.call(req)).await {
+        Ok(res) => Ok(res?.map_into_boxed_body()),
+        Err(_) => {
+            log::warn!("{} {} timed out after {:?}", method, path, timeout);
+            let res = HttpResponse::GatewayTimeout()
+                .json(json!({"error": "Request timed out", "code": "request_timeout"}));
+            Err(InternalError::from_response("Request timed out", res).into())
+        }
+    }
+}
+
+/// Start forgetting idle clients once this many are tracked
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Per-IP token buckets for write requests.
+///
+/// Each client may burst up to `per_minute` writes and regains one every
+/// `60 / per_minute` seconds. State is in memory, so it is per process and
+/// resets on restart.
+pub struct RateLimiter {
+    per_minute: u32,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    /// A limiter allowing `per_minute` writes per client; 0 disables limiting
+    pub fn new(per_minute: u32) -> Self {
+        RateLimiter {
+            per_minute,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token for `ip`, or return how long until one is available
+    fn check(&self, ip: IpAddr) -> Result<(), Duration> {
+        if self.per_minute == 0 {
+            return Ok(());
+        }
+        let capacity = f64::from(self.per_minute);
+        let per_second = capacity / 60.0;
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            // A bucket idle for a minute has refilled completely, so dropping
+            // it changes nothing for that client
+            buckets.retain(|_, b| now.duration_since(b.refilled_at) < Duration::from_secs(60));
+        }
+
+        let bucket = buckets.entry(ip).or_insert(Bucket {
+            tokens: capacity,
+            refilled_at: now,
+        });
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_second).min(capacity);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+        }
+    }
+}
+
+/// Throttle POST/PUT/PATCH/DELETE requests per client IP using the
+/// `RateLimiter` in app data; reads are never limited.
+///
+/// Over the limit the request is answered with 429 and a `Retry-After`
+/// header (whole seconds) without reaching the handler.
+pub async fn rate_limit(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let is_write = matches!(
+        *req.method(),
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    );
+    let limiter = req.app_data::<web::Data<RateLimiter>>().cloned();
+    let ip = req.peer_addr().map(|addr| addr.ip());
+
+    if let (true, Some(limiter), Some(ip)) = (is_write, limiter, ip) {
+        if let Err(wait) = limiter.check(ip) {
+            let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+            let res = HttpResponse::TooManyRequests()
+                .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+                .json(json!({
+                    "error": "Too many requests, please slow down",
+                    "code": "rate_limited"
+                }));
+            return Ok(req.into_response(res));
+        }
+    }
+
+    Ok(next.call(req).await?.map_into_boxed_body())
+}
+
+/// Prometheus metrics for the HTTP API, kept in app data.
+///
+/// Requests are labelled by route pattern (e.g. `/api/quizzes/{id}`) rather
+/// than the raw path so ids don't create a series each; requests that match
+/// no route share the `unmatched` label.
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    latency: HistogramVec,
+    pool_connections: IntGauge,
+    pool_active_connections: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests handled"),
+            &["method", "route", "status"],
+        )
+        .expect("valid metric");
+        let latency = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time spent handling HTTP requests",
+            ),
+            &["method", "route"],
+        )
+        .expect("valid metric");
+        let pool_connections = IntGauge::new(
+            "db_pool_connections",
+            "Open database connections in the pool",
+        )
+        .expect("valid metric");
+        let pool_active_connections = IntGauge::new(
+            "db_pool_active_connections",
+            "Database connections currently checked out of the pool",
+        )
+        .expect("valid metric");
+
+        let registry = Registry::new();
+        for collector in [
+            Box::new(requests.clone()) as Box<dyn Collector>,
+            Box::new(latency.clone()),
+            Box::new(pool_connections.clone()),
+            Box::new(pool_active_connections.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("metric names are unique");
+        }
+
+        Metrics {
+            registry,
+            requests,
+            latency,
+            pool_connections,
+            pool_active_connections,
+        }
+    }
+
+    fn observe(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        self.requests
+            .with_label_values(&[method, route, &status.to_string()])
+            .inc();
+        self.latency
+            .with_label_values(&[method, route])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Everything in the Prometheus text format, with the pool gauges read
+    /// from `pool` just now
+    pub fn render(&self, pool: &SqlitePool) -> Result<String, prometheus::Error> {
+        let size = i64::from(pool.size());
+        self.pool_connections.set(size);
+        self.pool_active_connections
+            .set(size - pool.num_idle() as i64);
+
+        let mut out = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut out)?;
+        Ok(String::from_utf8_lossy(&out).into_owned())
+    }
+}
+
+/// Count every request and time it with the `Metrics` in app data
+pub async fn record_metrics(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let metrics = req.app_data::<web::Data<Metrics>>().cloned();
+    let method = req.method().to_string();
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+    let started = Instant::now();
+
+    let result = next.call(req).await;
+
+    if let Some(metrics) = metrics {
+        let status = match &result {
+            Ok(res) => res.status(),
+            Err(e) => e.as_response_error().status_code(),
+        };
+        metrics.observe(&method, &route, status.as_u16(), started.elapsed());
+    }
+
+    Ok(result?.map_into_boxed_body())
+}
@@ -1,33 +1,892 @@
-use serde::{Deserialize, Serialize};
+use crate::tools;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Deserializer, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct Quiz {
     pub id: i32,
     pub title: String,
     pub description: Option<String>,
+    pub jlpt_level: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// RFC 3339 timestamps
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+    /// Incremented on every update; send it back with `PUT` to detect
+    /// concurrent edits
+    #[serde(default)]
+    pub version: i64,
+    /// Drafts are left out of `GET /api/quizzes` until published
+    #[serde(default)]
+    pub status: QuizStatus,
+    /// Starred with `POST /api/quizzes/{id}/favorite`
+    #[serde(default)]
+    pub favorite: bool,
     pub questions: Vec<Question>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Whether a quiz is listed publicly
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum QuizStatus {
+    /// Still being written; only reachable by id
+    #[default]
+    Draft,
+    Published,
+}
+
+impl QuizStatus {
+    /// Value stored in `quizzes.status`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QuizStatus::Draft => "draft",
+            QuizStatus::Published => "published",
+        }
+    }
+
+    /// Parse a stored value; anything unknown is treated as a draft
+    pub fn from_stored(value: Option<&str>) -> Self {
+        match value {
+            Some("published") => QuizStatus::Published,
+            _ => QuizStatus::Draft,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct Question {
     pub id: Option<i32>,
     pub text: String,
     pub options: Vec<String>,
+    #[serde(default)]
+    pub question_type: QuestionType,
+    /// The (first) correct option index
     pub correct_answer: i32,
+    /// Every correct option index, ascending; a single entry for
+    /// `QuestionType::Single`
+    #[serde(default)]
+    pub correct_answers: Vec<i32>,
+    /// Accepted answers for `QuestionType::Text` questions
+    #[serde(default)]
+    pub answer_text: Option<Vec<String>>,
+    /// Text questions only: require the answer in the same script (hiragana
+    /// vs katakana) as an accepted answer instead of treating them as equal
+    #[serde(default)]
+    pub exact_script: bool,
+    /// Kana reading shown as furigana above the question text
+    pub reading: Option<String>,
+    /// Pronunciation clip played for listening practice
+    #[serde(default)]
+    pub audio_url: Option<String>,
+    /// Why the correct answer is correct, shown after answering
+    #[serde(default)]
+    pub explanation: Option<String>,
+    /// A sentence using the vocab item, for context
+    #[serde(default)]
+    pub example_sentence: Option<String>,
+    /// Reading of `example_sentence`
+    #[serde(default)]
+    pub example_reading: Option<String>,
+    /// 1 (easiest) to 5 (hardest)
+    #[serde(default = "default_difficulty")]
+    pub difficulty: i32,
 }
 
-#[derive(Debug, Deserialize)]
+/// How a question is answered
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum QuestionType {
+    /// Exactly one correct option
+    #[default]
+    Single,
+    /// One or more correct options, all of which must be picked
+    Multiple,
+    /// Fill-in-the-blank: no options, the typed answer is compared against
+    /// `answer_text`
+    Text,
+    /// Reading practice: no options, the learner types the kana reading of
+    /// the question text, which must be given in `reading`
+    Reading,
+}
+
+impl QuestionType {
+    /// Value stored in `questions.question_type`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QuestionType::Single => "single",
+            QuestionType::Multiple => "multiple",
+            QuestionType::Text => "text",
+            QuestionType::Reading => "reading",
+        }
+    }
+
+    /// Parse a stored value; anything unknown (including NULL on old rows) is
+    /// treated as single-choice
+    pub fn from_stored(value: Option<&str>) -> Self {
+        match value {
+            Some("multiple") => QuestionType::Multiple,
+            Some("text") => QuestionType::Text,
+            Some("reading") => QuestionType::Reading,
+            _ => QuestionType::Single,
+        }
+    }
+}
+
+/// One entry of a submission: an option index, a list of indices for
+/// multiple-answer questions, or the typed answer for text questions
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum SubmittedAnswer {
+    Index(i32),
+    Indices(Vec<i32>),
+    Text(String),
+}
+
+/// Difficulty assumed for questions that don't specify one
+pub const DEFAULT_DIFFICULTY: i32 = 3;
+/// Allowed range for a question's difficulty
+pub const DIFFICULTY_RANGE: std::ops::RangeInclusive<i32> = 1..=5;
+
+fn default_difficulty() -> i32 {
+    DEFAULT_DIFFICULTY
+}
+
+impl Question {
+    /// Shuffle the options in place, moving `correct_answer` along with the
+    /// originally correct option so the two always agree.
+    pub fn shuffle_options<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        let mut order: Vec<usize> = (0..self.options.len()).collect();
+        order.shuffle(rng);
+
+        let remap = |index: i32| {
+            let old = usize::try_from(index).ok()?;
+            order.iter().position(|&o| o == old).map(|new| new as i32)
+        };
+        if let Some(new_index) = remap(self.correct_answer) {
+            self.correct_answer = new_index;
+        }
+        self.correct_answers = self
+            .correct_answers
+            .iter()
+            .map(|&i| remap(i).unwrap_or(i))
+            .collect();
+        self.correct_answers.sort_unstable();
+        if self.question_type == QuestionType::Multiple {
+            if let Some(&first) = self.correct_answers.first() {
+                self.correct_answer = first;
+            }
+        }
+        self.options = order.iter().map(|&old| self.options[old].clone()).collect();
+    }
+
+    /// Answers accepted for a typed question: `answer_text`, plus the
+    /// `reading` of a reading question
+    pub fn accepted_answers(&self) -> impl Iterator<Item = &String> {
+        let reading = match self.question_type {
+            QuestionType::Reading => self.reading.as_ref(),
+            _ => None,
+        };
+        self.answer_text.iter().flatten().chain(reading)
+    }
+
+    /// Whether `answer` is right. Multiple-answer questions are all-or-nothing:
+    /// the submitted set must match `correct_answers` exactly. Typed answers
+    /// are compared against `accepted_answers` after `tools::normalize_answer`
+    /// and, unless `exact_script` is set, `tools::fold_kana`.
+    pub fn is_correct(&self, answer: &SubmittedAnswer) -> bool {
+        match (self.question_type, answer) {
+            (QuestionType::Text | QuestionType::Reading, SubmittedAnswer::Text(text)) => {
+                let canonical = |s: &str| {
+                    let normalized = tools::normalize_answer(s);
+                    if self.exact_script {
+                        normalized
+                    } else {
+                        tools::fold_kana(&normalized)
+                    }
+                };
+                let typed = canonical(text);
+                self.accepted_answers()
+                    .any(|accepted| canonical(accepted) == typed)
+            }
+            (QuestionType::Text | QuestionType::Reading, _) | (_, SubmittedAnswer::Text(_)) => {
+                false
+            }
+            (QuestionType::Single, SubmittedAnswer::Index(i)) => *i == self.correct_answer,
+            (QuestionType::Single, SubmittedAnswer::Indices(v)) => v == &[self.correct_answer],
+            (QuestionType::Multiple, answer) => {
+                let mut chosen = match answer {
+                    SubmittedAnswer::Index(i) => vec![*i],
+                    SubmittedAnswer::Indices(v) => v.clone(),
+                    SubmittedAnswer::Text(_) => return false,
+                };
+                chosen.sort_unstable();
+                chosen.dedup();
+                chosen == self.correct_answers
+            }
+        }
+    }
+
+    /// The first option index of `answer` that doesn't name one of the
+    /// question's options, which makes the submission malformed rather than
+    /// wrong. Text answers have none.
+    pub fn invalid_option_index(&self, answer: &SubmittedAnswer) -> Option<i32> {
+        let in_range = |i: &i32| usize::try_from(*i).is_ok_and(|i| i < self.options.len());
+        match answer {
+            SubmittedAnswer::Index(i) => Some(*i).filter(|i| !in_range(i)),
+            SubmittedAnswer::Indices(v) => v.iter().copied().find(|i| !in_range(i)),
+            SubmittedAnswer::Text(_) => None,
+        }
+    }
+}
+
+/// Valid values for a quiz's `jlpt_level`, easiest first
+pub const JLPT_LEVELS: [&str; 5] = ["N5", "N4", "N3", "N2", "N1"];
+
+/// A recorded, scored submission of a quiz
+#[derive(Debug, Serialize, Clone)]
+pub struct Attempt {
+    pub id: i64,
+    pub quiz_id: i64,
+    pub score: i64,
+    pub total: i64,
+    pub answers: Vec<SubmittedAnswer>,
+    /// Milliseconds spent on each question, if the client reported them
+    pub timings: Option<Vec<u32>>,
+    pub created_at: Option<String>,
+}
+
+/// One problem found while validating a request body: the field it is
+/// about (a path such as `questions[2].options[1]`), the code of the
+/// problem (such as `option_empty`) and what is wrong
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, code: &'static str, message: impl Into<String>) -> Self {
+        FieldError {
+            field: field.into(),
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateQuizRequest {
     pub title: String,
     pub description: Option<String>,
+    /// One of `JLPT_LEVELS`
+    pub jlpt_level: Option<String>,
+    /// Topic tags; stored lowercased
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The `version` the client last read; required by `PUT`, ignored on create
+    pub version: Option<i64>,
     pub questions: Vec<QuestionInput>,
 }
 
-#[derive(Debug, Deserialize)]
+impl CreateQuizRequest {
+    /// Trim the title and description (a blank description becomes `None`)
+    /// and normalize every question; see `QuestionInput::normalize`
+    pub fn normalize(&mut self) {
+        self.title = self.title.trim().to_string();
+        self.description = self
+            .description
+            .as_deref()
+            .map(str::trim)
+            .filter(|d| !d.is_empty())
+            .map(str::to_string);
+        for question in &mut self.questions {
+            question.normalize();
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct QuestionInput {
     pub text: String,
     pub options: Vec<String>,
-    pub correct_answer: i32,
+    #[serde(default)]
+    pub question_type: QuestionType,
+    /// Correct option index; required for `single` questions
+    pub correct_answer: Option<i32>,
+    /// Correct option indices; required for `multiple` questions
+    #[serde(default)]
+    pub correct_answers: Vec<i32>,
+    /// Accepted answers; required for `text` questions
+    pub answer_text: Option<Vec<String>>,
+    /// Don't accept katakana for hiragana (or vice versa) in text answers
+    #[serde(default)]
+    pub exact_script: bool,
+    /// Kana reading shown as furigana above the question text
+    pub reading: Option<String>,
+    /// Pronunciation clip (an http or https URL)
+    pub audio_url: Option<String>,
+    /// Why the correct answer is correct, shown after answering
+    pub explanation: Option<String>,
+    /// A sentence using the vocab item, for context
+    pub example_sentence: Option<String>,
+    /// Reading of `example_sentence`; requires `example_sentence`
+    pub example_reading: Option<String>,
+    /// 1 (easiest) to 5 (hardest); defaults to `DEFAULT_DIFFICULTY`
+    pub difficulty: Option<i32>,
+}
+
+impl QuestionInput {
+    /// The correct option indices, ascending and without duplicates
+    pub fn correct_indices(&self) -> Vec<i32> {
+        let mut indices = match self.question_type {
+            QuestionType::Single => self.correct_answer.into_iter().collect(),
+            QuestionType::Multiple => self.correct_answers.clone(),
+            QuestionType::Text | QuestionType::Reading => Vec::new(),
+        };
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+
+    /// Check that the question has at least two distinct, non-blank options
+    /// and that its correct answer(s) point at them. Text questions instead
+    /// need no options and at least one non-blank accepted answer, and
+    /// reading questions a kana-only `reading`. Returns the first of
+    /// `problems`.
+    pub fn validate(&self) -> Result<(), FieldError> {
+        match self.problems().into_iter().next() {
+            Some(problem) => Err(problem),
+            None => Ok(()),
+        }
+    }
+
+    /// Every reason the question is invalid (see `validate`), in field order;
+    /// empty when it is valid
+    pub fn problems(&self) -> Vec<FieldError> {
+        let mut problems = Vec::new();
+        if self.question_type == QuestionType::Reading {
+            if !self.options.is_empty() {
+                problems.push(FieldError::new(
+                    "options",
+                    "options_not_allowed",
+                    "reading questions must not have options",
+                ));
+            }
+            match &self.reading {
+                Some(reading) if !tools::is_kana(reading) => problems.push(FieldError::new(
+                    "reading",
+                    "reading_not_kana",
+                    format!(
+                        "reading '{}' must be written in hiragana or katakana only",
+                        reading
+                    ),
+                )),
+                Some(_) => {}
+                None => problems.push(FieldError::new(
+                    "reading",
+                    "reading_required",
+                    "reading is required for reading questions",
+                )),
+            }
+        } else if self.question_type == QuestionType::Text {
+            if !self.options.is_empty() {
+                problems.push(FieldError::new(
+                    "options",
+                    "options_not_allowed",
+                    "text questions must not have options",
+                ));
+            }
+            let accepted = self.answer_text.as_deref().unwrap_or_default();
+            if !accepted
+                .iter()
+                .any(|a| !tools::normalize_answer(a).is_empty())
+            {
+                problems.push(FieldError::new(
+                    "answer_text",
+                    "answer_text_required",
+                    "answer_text must list at least one accepted answer",
+                ));
+            }
+        } else if self.options.len() < 2 {
+            problems.push(FieldError::new(
+                "options",
+                "too_few_options",
+                format!(
+                    "Question must have at least 2 options, got {}",
+                    self.options.len()
+                ),
+            ));
+        }
+        for (index, option) in self.options.iter().enumerate() {
+            let field = format!("options[{}]", index);
+            if option.trim().is_empty() {
+                problems.push(FieldError::new(
+                    field,
+                    "option_empty",
+                    format!("options[{}] must not be empty", index),
+                ));
+            } else if let Some(first) = self.options[..index].iter().position(|o| o == option) {
+                problems.push(FieldError::new(
+                    field,
+                    "option_duplicate",
+                    format!(
+                        "options[{}] duplicates options[{}] ('{}')",
+                        index, first, option
+                    ),
+                ));
+            }
+        }
+        let (field, indices) = match self.question_type {
+            QuestionType::Single => match self.correct_answer {
+                Some(index) => ("correct_answer", vec![index]),
+                None => {
+                    problems.push(FieldError::new(
+                        "correct_answer",
+                        "correct_answer_required",
+                        "correct_answer is required",
+                    ));
+                    ("correct_answer", Vec::new())
+                }
+            },
+            QuestionType::Multiple => {
+                if self.correct_answers.is_empty() {
+                    problems.push(FieldError::new(
+                        "correct_answers",
+                        "correct_answers_required",
+                        "correct_answers must list at least one option for multiple questions",
+                    ));
+                }
+                ("correct_answers", self.correct_answers.clone())
+            }
+            QuestionType::Text | QuestionType::Reading => ("correct_answer", Vec::new()),
+        };
+        for index in indices {
+            if index < 0 || index as usize >= self.options.len() {
+                problems.push(FieldError::new(
+                    field,
+                    "answer_out_of_range",
+                    format!(
+                        "{} {} is out of range for {} options",
+                        field,
+                        index,
+                        self.options.len()
+                    ),
+                ));
+            }
+        }
+        if let Some(audio_url) = &self.audio_url {
+            if let Err(message) = validate_audio_url(audio_url) {
+                problems.push(FieldError::new("audio_url", "invalid_audio_url", message));
+            }
+        }
+        if self.example_reading.is_some() && self.example_sentence.is_none() {
+            problems.push(FieldError::new(
+                "example_reading",
+                "example_sentence_required",
+                "example_reading requires example_sentence",
+            ));
+        }
+        if let Some(difficulty) = self.difficulty {
+            if !DIFFICULTY_RANGE.contains(&difficulty) {
+                problems.push(FieldError::new(
+                    "difficulty",
+                    "difficulty_out_of_range",
+                    format!(
+                        "difficulty {} must be between {} and {}",
+                        difficulty,
+                        DIFFICULTY_RANGE.start(),
+                        DIFFICULTY_RANGE.end()
+                    ),
+                ));
+            }
+        }
+        problems
+    }
+
+    /// NFKC-normalize the question text, options, reading and accepted
+    /// answers, so full-width and half-width input are stored the same way,
+    /// and trim surrounding whitespace from the text, options, reading,
+    /// audio URL, explanation and example (a blank one of the optional
+    /// fields becomes `None`)
+    pub fn normalize(&mut self) {
+        self.text = tools::nfkc(&self.text).trim().to_string();
+        for option in &mut self.options {
+            *option = tools::nfkc(option).trim().to_string();
+        }
+        self.reading = self
+            .reading
+            .as_deref()
+            .map(|r| tools::nfkc(r).trim().to_string())
+            .filter(|r| !r.is_empty());
+        self.audio_url = non_blank(self.audio_url.as_deref());
+        self.explanation = non_blank(self.explanation.as_deref());
+        self.example_sentence = non_blank(self.example_sentence.as_deref());
+        self.example_reading = non_blank(self.example_reading.as_deref());
+        for answer in self.answer_text.iter_mut().flatten() {
+            *answer = tools::nfkc(answer);
+        }
+    }
+
+    /// Apply the fields present in `patch` on top of this question
+    pub fn apply(&mut self, patch: UpdateQuestionRequest) {
+        if let Some(text) = patch.text {
+            self.text = text;
+        }
+        if let Some(options) = patch.options {
+            self.options = options;
+        }
+        if let Some(question_type) = patch.question_type {
+            self.question_type = question_type;
+        }
+        if let Some(correct_answer) = patch.correct_answer {
+            self.correct_answer = Some(correct_answer);
+        }
+        if let Some(correct_answers) = patch.correct_answers {
+            self.correct_answers = correct_answers;
+        }
+        if let Some(answer_text) = patch.answer_text {
+            self.answer_text = Some(answer_text);
+        }
+        if let Some(exact_script) = patch.exact_script {
+            self.exact_script = exact_script;
+        }
+        if let Some(reading) = patch.reading {
+            self.reading = reading;
+        }
+        if let Some(audio_url) = patch.audio_url {
+            self.audio_url = audio_url;
+        }
+        if let Some(explanation) = patch.explanation {
+            self.explanation = explanation;
+        }
+        if let Some(example_sentence) = patch.example_sentence {
+            self.example_sentence = example_sentence;
+        }
+        if let Some(example_reading) = patch.example_reading {
+            self.example_reading = example_reading;
+        }
+        if let Some(difficulty) = patch.difficulty {
+            self.difficulty = Some(difficulty);
+        }
+    }
+}
+
+/// `value` with surrounding whitespace trimmed, or `None` when that leaves
+/// nothing
+fn non_blank(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+/// Check that an `audio_url` is an absolute http(s) URL with a host
+fn validate_audio_url(audio_url: &str) -> Result<(), String> {
+    let parsed = url::Url::parse(audio_url)
+        .map_err(|e| format!("audio_url '{}' is not a valid URL: {}", audio_url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(format!(
+            "audio_url '{}' must be an http or https URL",
+            audio_url
+        ));
+    }
+    Ok(())
+}
+
+/// Body of `POST /api/quizzes/merge`
+#[derive(Debug, Deserialize)]
+pub struct MergeQuizzesRequest {
+    /// Quizzes whose questions are combined, in this order
+    pub source_ids: Vec<i64>,
+    pub title: String,
+}
+
+/// One line of `POST /api/import/all`: a create payload, plus the status
+/// and favorite flag that lines of `GET /api/export/all` carry (the other
+/// fields of an exported quiz are ignored)
+#[derive(Debug, Deserialize)]
+pub struct ImportedQuiz {
+    #[serde(flatten)]
+    pub quiz: CreateQuizRequest,
+    #[serde(default)]
+    pub status: QuizStatus,
+    #[serde(default)]
+    pub favorite: bool,
+}
+
+/// Body of `POST /api/quizzes/bulk-delete`
+#[derive(Debug, Deserialize)]
+pub struct BulkDeleteRequest {
+    pub ids: Vec<i64>,
+}
+
+/// Body of `POST /api/quizzes/{id}/questions/attach` and `.../detach`
+#[derive(Debug, Deserialize)]
+pub struct QuestionIdsRequest {
+    pub question_ids: Vec<i64>,
+}
+
+/// Query parameters accepted by `POST /api/quizzes/merge`
+#[derive(Debug, Deserialize)]
+pub struct MergeQuizzesQuery {
+    /// Soft-delete the source quizzes once merged (default false)
+    pub delete_sources: Option<bool>,
+}
+
+/// Body of `POST /api/quizzes/{id}/questions`: one question or an array
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum AddQuestionsRequest {
+    One(Box<QuestionInput>),
+    Many(Vec<QuestionInput>),
+}
+
+/// Deserialize a field that tells "absent" (`None`, with `serde(default)`)
+/// apart from `null` (`Some(None)`)
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Body of `PATCH /api/quizzes/{quiz_id}/questions/{question_id}`; omitted
+/// fields keep their stored value. `null` or a blank string clears
+/// `reading`, `audio_url`, `explanation`, `example_sentence` or
+/// `example_reading`.
+#[derive(Debug, Deserialize)]
+pub struct UpdateQuestionRequest {
+    pub text: Option<String>,
+    pub options: Option<Vec<String>>,
+    pub question_type: Option<QuestionType>,
+    pub correct_answer: Option<i32>,
+    pub correct_answers: Option<Vec<i32>>,
+    pub answer_text: Option<Vec<String>>,
+    pub exact_script: Option<bool>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub reading: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub audio_url: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub explanation: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub example_sentence: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub example_reading: Option<Option<String>>,
+    pub difficulty: Option<i32>,
+}
+
+/// Body of `PATCH /api/quizzes/{id}`; omitted fields keep their stored
+/// value and the questions are never touched. A blank `description` or
+/// `jlpt_level` clears it.
+#[derive(Debug, Deserialize)]
+pub struct UpdateQuizRequest {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub jlpt_level: Option<String>,
+    pub status: Option<QuizStatus>,
+    /// When given, the update only applies if the quiz is still at this
+    /// version
+    pub version: Option<i64>,
+}
+
+impl UpdateQuizRequest {
+    /// Whether the body sets any field at all
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.description.is_none()
+            && self.jlpt_level.is_none()
+            && self.status.is_none()
+    }
+}
+
+impl From<Question> for QuestionInput {
+    fn from(q: Question) -> Self {
+        QuestionInput {
+            text: q.text,
+            options: q.options,
+            question_type: q.question_type,
+            correct_answer: Some(q.correct_answer),
+            correct_answers: q.correct_answers,
+            answer_text: q.answer_text,
+            exact_script: q.exact_script,
+            reading: q.reading,
+            audio_url: q.audio_url,
+            explanation: q.explanation,
+            example_sentence: q.example_sentence,
+            example_reading: q.example_reading,
+            difficulty: Some(q.difficulty),
+        }
+    }
+}
+
+/// Body of `POST /api/quizzes/{id}/submit`: the chosen option index (or
+/// indices, for multiple-answer questions, or the typed text for text
+/// questions) for each question, in question order
+#[derive(Debug, Deserialize)]
+pub struct SubmitQuizRequest {
+    pub answers: Vec<SubmittedAnswer>,
+    /// Milliseconds spent on each question, in question order
+    #[serde(default)]
+    pub timings: Option<Vec<u32>>,
+}
+
+/// Body of `PUT /api/quizzes/{id}/questions/order`: every question id of the
+/// quiz, in the new order
+#[derive(Debug, Deserialize)]
+pub struct ReorderQuestionsRequest {
+    pub order: Vec<i64>,
+}
+
+/// Query parameters accepted by `GET /api/quizzes`
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListQuizzesQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    /// Substring match on the quiz title, ignoring case and full-width/
+    /// half-width differences (see `tools::title_key`)
+    pub q: Option<String>,
+    /// Only quizzes tagged with this JLPT level (e.g. `N5`)
+    pub level: Option<String>,
+    /// Only quizzes carrying this tag
+    pub tag: Option<String>,
+    /// Also list draft quizzes (default false)
+    pub include_drafts: Option<bool>,
+    /// Only favorite quizzes (default false)
+    pub favorites_only: Option<bool>,
+    /// Only quizzes created at or after this RFC 3339 time
+    pub created_after: Option<String>,
+    /// Only quizzes created before this RFC 3339 time
+    pub created_before: Option<String>,
+}
+
+/// Query parameters accepted by `POST /api/quizzes`
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CreateQuizQuery {
+    /// Reject the quiz with 409 if a non-deleted quiz already has the same
+    /// title, ignoring case and full-width/half-width differences
+    pub unique_title: Option<bool>,
+}
+
+/// Query parameters accepted by `GET /api/quizzes/{id}/leaderboard`
+#[derive(Debug, Deserialize)]
+pub struct LeaderboardQuery {
+    /// Number of entries (default 10, at most 50)
+    pub limit: Option<i64>,
+}
+
+/// Query parameters accepted by `GET /api/quizzes/{id}/export`
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    /// `csv` (default) or `anki`
+    pub format: Option<String>,
+}
+
+/// Query parameters accepted by `GET /api/search`
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    /// Substring to look for in question text and readings
+    pub q: Option<String>,
+}
+
+/// Query parameters accepted by `GET /api/quizzes/{id}`
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GetQuizQuery {
+    /// Return each question's options in random order
+    pub shuffle: Option<bool>,
+    /// `html` returns the description rendered from Markdown to sanitized
+    /// HTML instead of the stored Markdown
+    pub render: Option<String>,
+    /// Comma-separated question fields to return (see `QUESTION_FIELDS`),
+    /// e.g. `text,options`; `id` is always included
+    pub fields: Option<String>,
+}
+
+/// The `Question` fields `?fields=` can select
+pub const QUESTION_FIELDS: [&str; 14] = [
+    "id",
+    "text",
+    "options",
+    "question_type",
+    "correct_answer",
+    "correct_answers",
+    "answer_text",
+    "exact_script",
+    "reading",
+    "audio_url",
+    "explanation",
+    "example_sentence",
+    "example_reading",
+    "difficulty",
+];
+
+/// Query parameters accepted by `GET /api/quizzes/{id}/practice`
+#[derive(Debug, Deserialize)]
+pub struct PracticeQuery {
+    /// Only questions at or below this difficulty
+    pub max_difficulty: Option<i32>,
+}
+
+/// Query parameters accepted by `POST /api/quizzes/{id}/submit`
+#[derive(Debug, Deserialize)]
+pub struct SubmitQuizQuery {
+    /// Also score each question by its difficulty
+    pub weighted: Option<bool>,
+}
+
+/// Query parameters accepted by `GET /api/quizzes/{id}/random`
+#[derive(Debug, Deserialize)]
+pub struct RandomQuizQuery {
+    /// How many questions to draw (default 10)
+    pub count: Option<i64>,
+}
+
+/// Query parameters accepted by `GET /api/quizzes/{id}/distractors`
+#[derive(Debug, Deserialize)]
+pub struct DistractorsQuery {
+    /// Leave out each question's own correct option(s) (default false)
+    pub exclude_correct: Option<bool>,
+}
+
+/// Query parameters accepted by `POST /api/quizzes/{id}/variants`
+#[derive(Debug, Deserialize)]
+pub struct VariantsQuery {
+    /// How many variants to create (default 2, at most 26: A to Z)
+    pub count: Option<i64>,
+}
+
+/// Body for `POST /api/tools/romaji`
+#[derive(Debug, Deserialize)]
+pub struct RomajiRequest {
+    pub text: String,
+}
+
+/// Body for `POST /api/reviews`
+#[derive(Debug, Deserialize)]
+pub struct ReviewRequest {
+    /// Identifies the learner; reviews of different sessions are independent
+    pub session: String,
+    pub question_id: i64,
+    /// Recall quality from 0 (forgot) to 5 (perfect)
+    pub grade: i32,
+}
+
+/// Query parameters for `GET /api/reviews/due`
+#[derive(Debug, Deserialize)]
+pub struct DueReviewsQuery {
+    pub session: String,
+    /// Maximum number of questions to return (default 20, at most 100)
+    pub limit: Option<i64>,
 }
 
 // Test-related types and payloads removed because they are not used by current code.
@@ -0,0 +1,72 @@
+use crate::models::{
+    CreateQuizRequest, FieldError, Question, QuestionInput, QuestionType, Quiz, QuizStatus,
+};
+use crate::routes;
+use serde::Serialize;
+use utoipa::{OpenApi, ToSchema};
+use utoipa_swagger_ui::{Config, SwaggerUi};
+
+/// The `{ "error": ... }` envelope every failing endpoint responds with
+#[derive(Serialize, ToSchema)]
+pub struct ErrorResponse {
+    /// Human-readable message, in the language picked from `Accept-Language`
+    pub error: String,
+    /// Machine-readable error code (such as `quiz_not_found`), the same in
+    /// every language
+    pub code: String,
+    /// Id of the request, also sent as the `X-Request-Id` header
+    pub request_id: Option<String>,
+}
+
+/// Response of `GET /api/quizzes`
+#[derive(Serialize, ToSchema)]
+pub struct QuizPage {
+    pub quizzes: Vec<Quiz>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// Response of `POST /api/quizzes`
+#[derive(Serialize, ToSchema)]
+pub struct QuizCreated {
+    pub id: i64,
+    pub message: String,
+    /// Token to send as `X-Owner-Token` to change the quiz. Only returned
+    /// here, when the quiz is created; null when the create is retried with
+    /// its `Idempotency-Key`.
+    pub owner_token: Option<String>,
+    /// Questions whose reading doesn't fit the kanji of their text, when a
+    /// kanji readings file is configured; they are saved regardless
+    pub warnings: Vec<FieldError>,
+}
+
+/// OpenAPI document served at `/api/openapi.json`
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Japanese Vocab Quiz API",
+        description = "Create, browse and take Japanese vocabulary quizzes."
+    ),
+    paths(routes::list_quizzes, routes::get_quiz, routes::create_quiz),
+    components(schemas(
+        Quiz,
+        QuizStatus,
+        Question,
+        QuestionType,
+        CreateQuizRequest,
+        QuestionInput,
+        QuizPage,
+        QuizCreated,
+        FieldError,
+        ErrorResponse
+    ))
+)]
+pub struct ApiDoc;
+
+/// Swagger UI under `/swagger/`, reading the spec `/api/openapi.json`
+/// serves. Its assets are built into the binary (the `vendored` feature of
+/// utoipa-swagger-ui), so the page loads nothing from a CDN.
+pub fn swagger_ui() -> SwaggerUi {
+    SwaggerUi::new("/swagger/{_:.*}").config(Config::new(["/api/openapi.json"]))
+}
@@ -0,0 +1,62 @@
+use sqlx::SqlitePool;
+use std::time::Duration;
+
+/// Hard-delete quizzes soft-deleted more than `retention_days` ago, with
+/// their questions and idempotency keys (tags, attempts and review state go
+/// through the foreign keys). Returns the number of quizzes removed.
+pub async fn purge_deleted_quizzes(
+    pool: &SqlitePool,
+    retention_days: u32,
+) -> Result<u64, sqlx::Error> {
+    let cutoff = format!("-{} days", retention_days);
+    let expired =
+        "SELECT id FROM quizzes WHERE deleted_at IS NOT NULL AND deleted_at < datetime('now', ?)";
+
+    let mut tx = pool.begin().await?;
+    // questions.quiz_id is ON DELETE SET NULL, so remove them explicitly
+    // rather than leaving orphans behind
+    sqlx::query(&format!(
+        "DELETE FROM questions WHERE quiz_id IN ({})",
+        expired
+    ))
+    .bind(&cutoff)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(&format!(
+        "DELETE FROM idempotency_keys WHERE quiz_id IN ({})",
+        expired
+    ))
+    .bind(&cutoff)
+    .execute(&mut *tx)
+    .await?;
+    let purged = sqlx::query(&format!("DELETE FROM quizzes WHERE id IN ({})", expired))
+        .bind(&cutoff)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    tx.commit().await?;
+
+    Ok(purged)
+}
+
+/// Spawn the background task that purges expired soft-deleted quizzes every
+/// `interval`, starting right away. Each run borrows one connection from
+/// the pool, so request handlers keep the rest.
+pub fn spawn(pool: SqlitePool, interval: Duration, retention_days: u32) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // a run delayed by a busy database is not made up for with a burst
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match purge_deleted_quizzes(&pool, retention_days).await {
+                Ok(purged) => log::info!(
+                    "Purged {} quiz(zes) deleted more than {} day(s) ago",
+                    purged,
+                    retention_days
+                ),
+                Err(e) => log::error!("Failed to purge deleted quizzes: {}", e),
+            }
+        }
+    });
+}
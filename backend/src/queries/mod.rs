@@ -0,0 +1,629 @@
+//! SQL shared by the route handlers.
+//!
+//! Handlers load and store quizzes and questions through these functions
+//! instead of writing their own SELECT/INSERT statements, so each statement
+//! exists once. sqlx already caches the prepared statement of every query
+//! string per connection, which works best when the strings don't vary.
+
+use crate::error::log_error;
+use crate::models::{
+    CreateQuizRequest, Question, QuestionInput, QuestionType, Quiz, QuizStatus, DEFAULT_DIFFICULTY,
+};
+use crate::tools;
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, SqliteConnection, SqliteExecutor, SqlitePool};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Columns selected whenever quiz questions are loaded; see `question_from_row`.
+/// They are unambiguous when `questions` is joined with `quiz_questions`,
+/// which has the quiz id.
+pub const QUESTION_COLUMNS: &str =
+    "id, prompt, options, question_type, correct_answer, correct_index, correct_answers, answer_text, exact_script, reading, audio_url, explanation, example_sentence, example_reading, difficulty";
+
+/// Format a stored timestamp for the API as RFC 3339 in UTC with a `Z`
+/// suffix (`2024-05-01T09:30:00Z`).
+///
+/// The schema stores SQLite `datetime('now')` values, which are UTC without
+/// an offset (`YYYY-MM-DD HH:MM:SS`); values written with an offset are
+/// converted to UTC. Anything else is returned unchanged.
+pub fn rfc3339(raw: Option<String>) -> Option<String> {
+    let raw = raw?;
+    let utc = NaiveDateTime::parse_from_str(&raw, "%Y-%m-%d %H:%M:%S")
+        .map(|dt| dt.and_utc())
+        .or_else(|_| DateTime::parse_from_rfc3339(&raw).map(|dt| dt.with_timezone(&Utc)));
+    match utc {
+        Ok(dt) => Some(dt.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        Err(_) => Some(raw),
+    }
+}
+
+/// Condition on the `questions` table matching bank questions: ones not
+/// written for a quiz nor generated from an entry
+pub const BANK_QUESTION: &str = "questions.quiz_id IS NULL AND questions.entry_id IS NULL";
+
+/// Build a `Question` from a `questions` row.
+///
+/// Quiz questions keep the chosen option index in `correct_index` and the
+/// answer text in `correct_answer`, matching the generated question bank.
+/// Rows written before that carried the index as text in `correct_answer`,
+/// so that is parsed as a fallback. `correct_answers` (a JSON array) lists
+/// every correct index and falls back to the single index when absent; text
+/// questions keep their accepted answers in `answer_text`, also JSON.
+pub fn question_from_row(r: &SqliteRow) -> Question {
+    let id: i64 = r.try_get("id").unwrap_or(0);
+    let text: String = r.try_get("prompt").unwrap_or_default();
+    let options_text: String = r.try_get("options").unwrap_or_else(|_| "[]".to_string());
+    let options: Vec<String> = serde_json::from_str(&options_text).unwrap_or_default();
+    let correct_answer: i64 = r
+        .try_get::<i64, _>("correct_index")
+        .ok()
+        .or_else(|| {
+            r.try_get::<String, _>("correct_answer")
+                .ok()
+                .and_then(|s| s.trim().parse().ok())
+        })
+        .unwrap_or(0);
+    let question_type = QuestionType::from_stored(
+        r.try_get::<Option<String>, _>("question_type")
+            .ok()
+            .flatten()
+            .as_deref(),
+    );
+    let correct_answers: Vec<i32> = r
+        .try_get::<Option<String>, _>("correct_answers")
+        .ok()
+        .flatten()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_else(|| vec![correct_answer as i32]);
+    let answer_text: Option<Vec<String>> = r
+        .try_get::<Option<String>, _>("answer_text")
+        .ok()
+        .flatten()
+        .and_then(|s| serde_json::from_str(&s).ok());
+    let exact_script: bool = r
+        .try_get::<Option<bool>, _>("exact_script")
+        .ok()
+        .flatten()
+        .unwrap_or(false);
+    let reading: Option<String> = r.try_get("reading").ok().flatten();
+    let audio_url: Option<String> = r.try_get("audio_url").ok().flatten();
+    let explanation: Option<String> = r.try_get("explanation").ok().flatten();
+    let example_sentence: Option<String> = r.try_get("example_sentence").ok().flatten();
+    let example_reading: Option<String> = r.try_get("example_reading").ok().flatten();
+    let difficulty: i64 = r
+        .try_get::<Option<i64>, _>("difficulty")
+        .ok()
+        .flatten()
+        .unwrap_or(DEFAULT_DIFFICULTY as i64);
+
+    Question {
+        id: Some(id as i32),
+        text,
+        options,
+        question_type,
+        correct_answer: correct_answer as i32,
+        correct_answers,
+        answer_text,
+        exact_script,
+        reading,
+        audio_url,
+        explanation,
+        example_sentence,
+        example_reading,
+        difficulty: difficulty as i32,
+    }
+}
+
+/// Columns selected whenever quiz rows are loaded; see `quiz_from_row`.
+pub const QUIZ_COLUMNS: &str =
+    "id, title, description, jlpt_level, created_at, updated_at, version, status, favorite";
+
+/// Build a `Quiz` from a `quizzes` row plus its already-loaded tags and questions
+pub fn quiz_from_row(r: &SqliteRow, tags: Vec<String>, questions: Vec<Question>) -> Quiz {
+    Quiz {
+        id: r.try_get::<i64, _>("id").unwrap_or(0) as i32,
+        title: r.try_get::<String, _>("title").unwrap_or_default(),
+        description: r.try_get::<Option<String>, _>("description").ok().flatten(),
+        jlpt_level: r.try_get("jlpt_level").ok().flatten(),
+        tags,
+        created_at: rfc3339(r.try_get("created_at").ok().flatten()),
+        updated_at: rfc3339(r.try_get("updated_at").ok().flatten()),
+        version: r.try_get("version").unwrap_or(1),
+        status: QuizStatus::from_stored(r.try_get::<String, _>("status").ok().as_deref()),
+        favorite: r.try_get("favorite").unwrap_or(false),
+        questions,
+    }
+}
+
+/// Load a quiz with its questions. Returns `Ok(None)` when no quiz has this id
+/// or it has been soft-deleted.
+pub async fn fetch_quiz(pool: &SqlitePool, id: i64) -> Result<Option<Quiz>, sqlx::Error> {
+    let sql = format!(
+        "SELECT {} FROM quizzes WHERE id = ? AND deleted_at IS NULL",
+        QUIZ_COLUMNS
+    );
+    let quiz_row = match sqlx::query(&sql).bind(id).fetch_optional(pool).await? {
+        Some(r) => r,
+        None => return Ok(None),
+    };
+
+    let questions = fetch_questions_for(pool, &[id])
+        .await?
+        .remove(&id)
+        .unwrap_or_default();
+    let tags = fetch_tags(pool, &[id])
+        .await?
+        .remove(&id)
+        .unwrap_or_default();
+
+    Ok(Some(quiz_from_row(&quiz_row, tags, questions)))
+}
+
+/// Load up to `limit` non-deleted quizzes (drafts included) with ids above
+/// `after_id`, in id order, with their tags and questions. Passing the last
+/// id of one batch as `after_id` of the next walks every quiz.
+pub async fn fetch_quiz_batch(
+    pool: &SqlitePool,
+    after_id: i64,
+    limit: i64,
+) -> Result<Vec<Quiz>, sqlx::Error> {
+    let sql = format!(
+        "SELECT {} FROM quizzes WHERE id > ? AND deleted_at IS NULL ORDER BY id LIMIT ?",
+        QUIZ_COLUMNS
+    );
+    let rows = sqlx::query(&sql)
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+    let ids: Vec<i64> = rows.iter().map(|r| r.try_get("id").unwrap_or(0)).collect();
+    let mut questions = fetch_questions_for(pool, &ids).await?;
+    let mut tags = fetch_tags(pool, &ids).await?;
+
+    Ok(rows
+        .iter()
+        .zip(&ids)
+        .map(|(r, id)| {
+            quiz_from_row(
+                r,
+                tags.remove(id).unwrap_or_default(),
+                questions.remove(id).unwrap_or_default(),
+            )
+        })
+        .collect())
+}
+
+/// Load the questions of each of `quiz_ids` in quiz order, keyed by quiz id.
+/// A question attached to several of them is in each list.
+///
+/// One query for the whole batch, so listing a page of quizzes doesn't issue
+/// a query per quiz.
+pub async fn fetch_questions_for(
+    pool: &SqlitePool,
+    quiz_ids: &[i64],
+) -> Result<HashMap<i64, Vec<Question>>, sqlx::Error> {
+    let mut questions: HashMap<i64, Vec<Question>> = HashMap::new();
+    if quiz_ids.is_empty() {
+        return Ok(questions);
+    }
+
+    let placeholders = vec!["?"; quiz_ids.len()].join(", ");
+    let sql = format!(
+        "SELECT qq.quiz_id, {} FROM quiz_questions qq JOIN questions ON questions.id = qq.question_id WHERE qq.quiz_id IN ({}) ORDER BY qq.position, questions.id",
+        QUESTION_COLUMNS, placeholders
+    );
+    let mut q = sqlx::query(&sql);
+    for id in quiz_ids {
+        q = q.bind(id);
+    }
+
+    for r in q.fetch_all(pool).await? {
+        let quiz_id: i64 = r.try_get("quiz_id").unwrap_or(0);
+        questions
+            .entry(quiz_id)
+            .or_default()
+            .push(question_from_row(&r));
+    }
+    Ok(questions)
+}
+
+/// Up to `count` questions of `quiz_id`, in random order
+pub async fn fetch_random_questions(
+    pool: &SqlitePool,
+    quiz_id: i64,
+    count: i64,
+) -> Result<Vec<Question>, sqlx::Error> {
+    let sql = format!(
+        "SELECT {} FROM quiz_questions qq JOIN questions ON questions.id = qq.question_id WHERE qq.quiz_id = ? ORDER BY random() LIMIT ?",
+        QUESTION_COLUMNS
+    );
+    let rows = sqlx::query(&sql)
+        .bind(quiz_id)
+        .bind(count)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(question_from_row).collect())
+}
+
+/// Load the tag names of each of `quiz_ids`, keyed by quiz id
+pub async fn fetch_tags(
+    pool: &SqlitePool,
+    quiz_ids: &[i64],
+) -> Result<HashMap<i64, Vec<String>>, sqlx::Error> {
+    let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+    if quiz_ids.is_empty() {
+        return Ok(tags);
+    }
+
+    let placeholders = vec!["?"; quiz_ids.len()].join(", ");
+    let sql = format!(
+        "SELECT qt.quiz_id, t.name FROM quiz_tags qt JOIN tags t ON t.id = qt.tag_id WHERE qt.quiz_id IN ({}) ORDER BY t.name",
+        placeholders
+    );
+    let mut q = sqlx::query(&sql);
+    for id in quiz_ids {
+        q = q.bind(id);
+    }
+
+    for r in q.fetch_all(pool).await? {
+        let quiz_id: i64 = r.try_get("quiz_id").unwrap_or(0);
+        let name: String = r.try_get("name").unwrap_or_default();
+        tags.entry(quiz_id).or_default().push(name);
+    }
+    Ok(tags)
+}
+
+/// Trim and lowercase tag names, dropping empty and duplicate ones
+fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+/// Replace the tags of `quiz_id`, creating tag rows that don't exist yet
+pub async fn set_quiz_tags(
+    conn: &mut SqliteConnection,
+    quiz_id: i64,
+    tags: &[String],
+) -> Result<(), &'static str> {
+    sqlx::query("DELETE FROM quiz_tags WHERE quiz_id = ?")
+        .bind(quiz_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| log_error("Failed to save tags", e))?;
+
+    for name in normalize_tags(tags) {
+        sqlx::query("INSERT OR IGNORE INTO tags (name) VALUES (?)")
+            .bind(&name)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| log_error("Failed to save tags", e))?;
+        sqlx::query(
+            "INSERT OR IGNORE INTO quiz_tags (quiz_id, tag_id) SELECT ?, id FROM tags WHERE name = ?",
+        )
+        .bind(quiz_id)
+        .bind(&name)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| log_error("Failed to save tags", e))?;
+    }
+    Ok(())
+}
+
+/// Load one question, but only if it belongs to `quiz_id` and that quiz has not
+/// been soft-deleted
+pub async fn fetch_question(
+    pool: &SqlitePool,
+    quiz_id: i64,
+    question_id: i64,
+) -> Result<Option<Question>, sqlx::Error> {
+    let sql = format!(
+        "SELECT {} FROM quiz_questions qq JOIN questions ON questions.id = qq.question_id WHERE qq.question_id = ? AND qq.quiz_id = ? AND qq.quiz_id IN (SELECT id FROM quizzes WHERE deleted_at IS NULL)",
+        QUESTION_COLUMNS
+    );
+    let row = sqlx::query(&sql)
+        .bind(question_id)
+        .bind(quiz_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.as_ref().map(question_from_row))
+}
+
+/// Load the questions with the given ids, in that order; ids with no
+/// question are skipped
+pub async fn fetch_questions_by_id(
+    pool: &SqlitePool,
+    ids: &[i64],
+) -> Result<Vec<Question>, sqlx::Error> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let sql = format!(
+        "SELECT {} FROM questions WHERE id IN ({})",
+        QUESTION_COLUMNS,
+        vec!["?"; ids.len()].join(", ")
+    );
+    let mut q = sqlx::query(&sql);
+    for id in ids {
+        q = q.bind(id);
+    }
+    let mut by_id: HashMap<i64, Question> = q
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(|r| (r.try_get("id").unwrap_or(0), question_from_row(r)))
+        .collect();
+    Ok(ids.iter().filter_map(|id| by_id.remove(id)).collect())
+}
+
+/// Insert `questions` for `quiz_id` on the given connection (normally an open
+/// transaction), after any questions the quiz already has. Returns the new
+/// question ids; on failure returns the error message for the JSON response.
+pub async fn insert_questions(
+    conn: &mut SqliteConnection,
+    quiz_id: i64,
+    questions: &[QuestionInput],
+) -> Result<Vec<i64>, &'static str> {
+    let mut ids = Vec::with_capacity(questions.len());
+    for question in questions {
+        ids.push(insert_question_row(conn, Some(quiz_id), question).await?);
+    }
+    attach_questions(conn, quiz_id, &ids).await?;
+    Ok(ids)
+}
+
+/// Insert `questions` into the bank, attached to no quiz. Returns the new
+/// question ids; on failure returns the error message for the JSON response.
+pub async fn insert_bank_questions(
+    conn: &mut SqliteConnection,
+    questions: &[QuestionInput],
+) -> Result<Vec<i64>, &'static str> {
+    let mut ids = Vec::with_capacity(questions.len());
+    for question in questions {
+        ids.push(insert_question_row(conn, None, question).await?);
+    }
+    Ok(ids)
+}
+
+/// Insert one `questions` row written for `quiz_id` (`None` for the bank)
+async fn insert_question_row(
+    conn: &mut SqliteConnection,
+    quiz_id: Option<i64>,
+    question: &QuestionInput,
+) -> Result<i64, &'static str> {
+    let options_json = serde_json::to_string(&question.options)
+        .map_err(|e| log_error("Failed to serialize question options", e))?;
+    let answer = stored_answer(question);
+
+    let res = sqlx::query(
+        "INSERT INTO questions (quiz_id, prompt, options, question_type, correct_answer, correct_index, correct_answers, answer_text, exact_script, reading, audio_url, explanation, example_sentence, example_reading, difficulty) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(quiz_id)
+    .bind(&question.text)
+    .bind(&options_json)
+    .bind(question.question_type.as_str())
+    .bind(&answer.text)
+    .bind(answer.index)
+    .bind(&answer.indices_json)
+    .bind(&answer.accepted_json)
+    .bind(question.exact_script)
+    .bind(&question.reading)
+    .bind(&question.audio_url)
+    .bind(&question.explanation)
+    .bind(&question.example_sentence)
+    .bind(&question.example_reading)
+    .bind(question.difficulty.unwrap_or(DEFAULT_DIFFICULTY))
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| log_error("Failed to create questions", e))?;
+    Ok(res.last_insert_rowid())
+}
+
+/// Add existing questions to the end of `quiz_id`, in the order given. On
+/// failure returns the error message for the JSON response.
+pub async fn attach_questions(
+    conn: &mut SqliteConnection,
+    quiz_id: i64,
+    question_ids: &[i64],
+) -> Result<(), &'static str> {
+    let next_position: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(MAX(position) + 1, 0) FROM quiz_questions WHERE quiz_id = ?",
+    )
+    .bind(quiz_id)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| log_error("Failed to create questions", e))?;
+
+    for (offset, question_id) in question_ids.iter().enumerate() {
+        sqlx::query("INSERT INTO quiz_questions (quiz_id, question_id, position) VALUES (?, ?, ?)")
+            .bind(quiz_id)
+            .bind(question_id)
+            .bind(next_position + offset as i64)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| log_error("Failed to create questions", e))?;
+    }
+    Ok(())
+}
+
+/// Take every question out of `quiz_id`: the ones written for it are
+/// deleted, bank questions are only detached. Returns how many questions
+/// the quiz had.
+pub async fn remove_quiz_questions(
+    conn: &mut SqliteConnection,
+    quiz_id: i64,
+) -> Result<u64, sqlx::Error> {
+    let removed = sqlx::query("DELETE FROM quiz_questions WHERE quiz_id = ?")
+        .bind(quiz_id)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+    sqlx::query("DELETE FROM questions WHERE quiz_id = ?")
+        .bind(quiz_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(removed)
+}
+
+/// Renumber the questions of `quiz_id` as 0, 1, 2... keeping their order,
+/// after some were taken out
+pub async fn renumber_quiz_questions(
+    executor: impl SqliteExecutor<'_>,
+    quiz_id: i64,
+) -> Result<(), sqlx::Error> {
+    // each question's position becomes the number of questions ordered
+    // before it
+    sqlx::query(
+        "UPDATE quiz_questions SET position = (SELECT COUNT(*) FROM quiz_questions q2 WHERE q2.quiz_id = quiz_questions.quiz_id AND (q2.position < quiz_questions.position OR (q2.position = quiz_questions.position AND q2.question_id < quiz_questions.question_id))) WHERE quiz_id = ?",
+    )
+    .bind(quiz_id)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// The answer columns of a `questions` row
+struct StoredAnswer {
+    /// `correct_answer`: the text of the (first) correct option
+    text: Option<String>,
+    /// `correct_index`: the (first) correct option index
+    index: Option<i64>,
+    /// `correct_answers`: every correct index, as a JSON array
+    indices_json: String,
+    /// `answer_text`: accepted answers of a text question, as a JSON array
+    accepted_json: Option<String>,
+}
+
+/// Work out the answer columns for a validated question
+fn stored_answer(question: &QuestionInput) -> StoredAnswer {
+    let indices = question.correct_indices();
+    let first = indices.first().copied();
+    let accepted = match question.question_type {
+        QuestionType::Text | QuestionType::Reading => question.answer_text.as_ref(),
+        _ => None,
+    };
+    let reading = match question.question_type {
+        QuestionType::Reading => question.reading.as_ref(),
+        _ => None,
+    };
+    StoredAnswer {
+        text: first
+            .and_then(|i| usize::try_from(i).ok())
+            .and_then(|i| question.options.get(i))
+            .or(reading)
+            .or_else(|| accepted.and_then(|a| a.first()))
+            .cloned(),
+        index: first.map(i64::from),
+        indices_json: serde_json::to_string(&indices).unwrap_or_else(|_| "[]".to_string()),
+        accepted_json: accepted.and_then(|a| serde_json::to_string(a).ok()),
+    }
+}
+
+/// Hex SHA-256 of an owner token, as stored in `quizzes.owner_token_hash`
+pub fn hash_owner_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Insert a quiz row and its questions on the given connection (normally an
+/// open transaction), returning the new quiz id and its owner token. On
+/// failure returns the error message for the JSON response.
+///
+/// Every quiz gets a fresh owner token, whichever route creates it; only its
+/// hash is stored, so the caller must hand the token out now or never.
+pub async fn insert_quiz(
+    conn: &mut SqliteConnection,
+    quiz: &CreateQuizRequest,
+) -> Result<(i64, String), &'static str> {
+    let owner_token = Uuid::new_v4().to_string();
+    let res = sqlx::query(
+        "INSERT INTO quizzes (title, title_key, description, jlpt_level, owner_token_hash, updated_at) VALUES (?, ?, ?, ?, ?, datetime('now'))",
+    )
+    .bind(&quiz.title)
+    .bind(tools::title_key(&quiz.title))
+    .bind(&quiz.description)
+    .bind(&quiz.jlpt_level)
+    .bind(hash_owner_token(&owner_token))
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| log_error("Failed to create quiz", e))?;
+
+    let quiz_id = res.last_insert_rowid();
+    insert_questions(conn, quiz_id, &quiz.questions).await?;
+    set_quiz_tags(conn, quiz_id, &quiz.tags).await?;
+
+    Ok((quiz_id, owner_token))
+}
+
+/// Overwrite an existing question of `quiz_id` with a validated question
+/// (for a bank question, in every quiz it is attached to). Returns `false`
+/// when `quiz_id` has no such question, so nothing was written; on failure
+/// returns the error message for the JSON response.
+pub async fn update_question(
+    conn: &mut SqliteConnection,
+    quiz_id: i64,
+    question_id: i64,
+    question: &QuestionInput,
+) -> Result<bool, &'static str> {
+    let options_json = serde_json::to_string(&question.options)
+        .map_err(|e| log_error("Failed to serialize question options", e))?;
+    let answer = stored_answer(question);
+
+    let res = sqlx::query(
+        "UPDATE questions SET prompt = ?, options = ?, question_type = ?, correct_answer = ?, correct_index = ?, correct_answers = ?, answer_text = ?, exact_script = ?, reading = ?, audio_url = ?, explanation = ?, example_sentence = ?, example_reading = ?, difficulty = ? WHERE id = ? AND id IN (SELECT question_id FROM quiz_questions WHERE quiz_id = ?)",
+    )
+    .bind(&question.text)
+    .bind(&options_json)
+    .bind(question.question_type.as_str())
+    .bind(&answer.text)
+    .bind(answer.index)
+    .bind(&answer.indices_json)
+    .bind(&answer.accepted_json)
+    .bind(question.exact_script)
+    .bind(&question.reading)
+    .bind(&question.audio_url)
+    .bind(&question.explanation)
+    .bind(&question.example_sentence)
+    .bind(&question.example_reading)
+    .bind(question.difficulty.unwrap_or(DEFAULT_DIFFICULTY))
+    .bind(question_id)
+    .bind(quiz_id)
+    .execute(conn)
+    .await
+    .map_err(|e| log_error("Failed to update question", e))?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Id of a non-deleted quiz whose title matches `title` ignoring case and
+/// full-width/half-width differences (see `tools::title_key`)
+pub async fn quiz_id_with_title(
+    executor: impl SqliteExecutor<'_>,
+    title: &str,
+) -> Result<Option<i64>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT id FROM quizzes WHERE title_key = ? AND deleted_at IS NULL ORDER BY id LIMIT 1",
+    )
+    .bind(tools::title_key(title))
+    .fetch_optional(executor)
+    .await
+}
+
+/// Set `updated_at` of `quiz_id` to now, after its questions changed
+pub async fn touch_quiz(
+    executor: impl SqliteExecutor<'_>,
+    quiz_id: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE quizzes SET updated_at = datetime('now') WHERE id = ?")
+        .bind(quiz_id)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use crate::tools;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Readings of kanji, used to warn about question readings that can't be
+/// right for the question text.
+///
+/// Loaded from the file named by `KANJI_READINGS_PATH`, one kanji per line
+/// followed by its readings in hiragana or katakana, separated by spaces,
+/// tabs or commas (`水 すい みず`). KANJIDIC-style `.` and `-` markers are
+/// ignored and lines starting with `#` are comments. Without a file the map
+/// is empty and `check` never warns.
+#[derive(Debug, Default)]
+pub struct KanjiReadings {
+    readings: HashMap<char, Vec<String>>,
+}
+
+/// Whether `c` is a kanji (CJK unified ideograph or the repeat mark 々)
+fn is_kanji(c: char) -> bool {
+    matches!(c, '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '々')
+}
+
+/// The voiced (and for the h-row, half-voiced) forms of a hiragana, which a
+/// reading takes in compounds (rendaku: 本 ほん in 三本 さんぼん)
+fn voiced_forms(c: char) -> Vec<char> {
+    let next = |offset: u32| char::from_u32(c as u32 + offset);
+    if "かきくけこさしすせそたちつてと".contains(c) {
+        next(1).into_iter().collect()
+    } else if "はひふへほ".contains(c) {
+        [next(1), next(2)].into_iter().flatten().collect()
+    } else {
+        Vec::new()
+    }
+}
+
+impl KanjiReadings {
+    /// Read the map from `path`; see the type docs for the format
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut readings: HashMap<char, Vec<String>> = HashMap::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split(|c: char| c.is_whitespace() || c == ',' || c == '、');
+            let kanji = fields.next().unwrap_or_default();
+            let mut chars = kanji.chars();
+            let (Some(kanji), None) = (chars.next(), chars.next()) else {
+                return Err(format!(
+                    "line {}: '{}' is not a single kanji",
+                    index + 1,
+                    kanji
+                ));
+            };
+            let entry = readings.entry(kanji).or_default();
+            for reading in fields {
+                let reading: String = reading
+                    .chars()
+                    .filter(|c| !matches!(c, '.' | '-'))
+                    .collect();
+                if reading.is_empty() {
+                    continue;
+                }
+                if !tools::is_kana(&reading) {
+                    return Err(format!(
+                        "line {}: reading '{}' of {} is not kana",
+                        index + 1,
+                        reading,
+                        kanji
+                    ));
+                }
+                entry.push(tools::fold_kana(&reading));
+            }
+        }
+        Ok(KanjiReadings { readings })
+    }
+
+    /// Number of kanji with readings
+    pub fn kanji_count(&self) -> usize {
+        self.readings.len()
+    }
+
+    /// A warning when `reading` uses kana that no reading of the kanji in
+    /// `text` (nor the kana written in `text` itself, such as okurigana)
+    /// accounts for, allowing for voicing and the small っ of compounds.
+    ///
+    /// Returns `None` when `text` has no kanji or one the map doesn't know,
+    /// since nothing can be said about its reading then.
+    pub fn check(&self, text: &str, reading: &str) -> Option<String> {
+        let kanji: Vec<char> = text.chars().filter(|c| is_kanji(*c)).collect();
+        if kanji.is_empty() {
+            return None;
+        }
+
+        let mut expected: HashSet<char> = ['っ', 'ー'].into_iter().collect();
+        for k in &kanji {
+            // 々 repeats the kanji before it, whose readings are already in
+            if *k == '々' {
+                continue;
+            }
+            for r in self.readings.get(k)? {
+                expected.extend(r.chars());
+            }
+        }
+        expected.extend(
+            tools::fold_kana(text)
+                .chars()
+                .filter(|c| tools::is_kana(&c.to_string())),
+        );
+        let voiced: Vec<char> = expected.iter().flat_map(|c| voiced_forms(*c)).collect();
+        expected.extend(voiced);
+
+        let mut unexpected: Vec<char> = Vec::new();
+        for c in tools::fold_kana(reading).chars() {
+            if !expected.contains(&c) && !unexpected.contains(&c) {
+                unexpected.push(c);
+            }
+        }
+        if unexpected.is_empty() {
+            return None;
+        }
+        let kanji: String = kanji.into_iter().collect();
+        Some(format!(
+            "reading '{}' uses {} which no reading of {} has",
+            reading,
+            unexpected.into_iter().collect::<String>(),
+            kanji
+        ))
+    }
+}
@@ -1,181 +1,2858 @@
-use crate::models::{CreateQuizRequest, Question, Quiz};
-use actix_web::{web, HttpResponse, Responder};
-use chrono::Utc;
+use crate::cache::QuestionCounts;
+use crate::config::Config;
+use crate::db::DbHealth;
+use crate::error::{log_error, ApiError};
+use crate::export;
+use crate::import;
+use crate::middleware::Metrics;
+use crate::models::{
+    AddQuestionsRequest, Attempt, BulkDeleteRequest, CreateQuizQuery, CreateQuizRequest,
+    DistractorsQuery, DueReviewsQuery, ExportQuery, FieldError, GetQuizQuery, ImportedQuiz,
+    LeaderboardQuery, ListQuizzesQuery, MergeQuizzesQuery, MergeQuizzesRequest, PracticeQuery,
+    QuestionIdsRequest, QuestionInput, Quiz, QuizStatus, RandomQuizQuery, ReorderQuestionsRequest,
+    ReviewRequest, RomajiRequest, SearchQuery, SubmitQuizQuery, SubmitQuizRequest, SubmittedAnswer,
+    UpdateQuestionRequest, UpdateQuizRequest, VariantsQuery, DIFFICULTY_RANGE, JLPT_LEVELS,
+    QUESTION_FIELDS,
+};
+use crate::openapi::{self, ApiDoc};
+use crate::queries::{
+    self, fetch_question, fetch_questions_for, fetch_quiz, fetch_random_questions, fetch_tags,
+    hash_owner_token, insert_questions, insert_quiz, question_from_row, quiz_from_row, rfc3339,
+    set_quiz_tags, touch_quiz, QUESTION_COLUMNS, QUIZ_COLUMNS,
+};
+use crate::readings::KanjiReadings;
+use crate::srs;
+use crate::tools;
+use actix_web::error::{InternalError, JsonPayloadError};
+use actix_web::http::header::{self, Header};
+use actix_web::{web, HttpRequest, HttpResponse, Responder, ResponseError};
+use chrono::{DateTime, Datelike, NaiveDate, SecondsFormat, Utc};
+use futures_util::StreamExt;
+use rand::seq::SliceRandom;
 use serde_json::{json, Value as JsonValue};
+use sha2::{Digest, Sha256};
 use sqlx::sqlite::SqliteRow;
-use sqlx::{Row, SqlitePool};
+use sqlx::{Row, SqliteConnection, SqlitePool};
+use std::collections::{HashMap, HashSet};
+use utoipa::OpenApi;
 
-/// List all quizzes
-pub async fn list_quizzes(pool: web::Data<SqlitePool>) -> impl Responder {
+/// Most questions a single quiz may hold
+pub const MAX_QUESTIONS: usize = 500;
+
+/// Longest quiz title, counted in characters (Unicode scalar values) rather
+/// than bytes, since a kana or kanji takes three bytes in UTF-8.
+/// `quizzes.title` is `TEXT`, so the database itself never truncates it.
+const MAX_TITLE_CHARS: usize = 255;
+
+/// Validate a create/update payload, collecting every problem rather than
+/// stopping at the first. On failure returns the 400 body
+/// `{ error, errors: [{ field, message }] }`; question fields are prefixed
+/// with their index, as in `questions[2].correct_answer`, and
+/// `question_index` names the first invalid question.
+fn validate_quiz_request(quiz: &CreateQuizRequest) -> Result<(), JsonValue> {
+    let mut errors = quiz_metadata_errors(&quiz.title, quiz.jlpt_level.as_deref());
+
+    if quiz.questions.is_empty() {
+        errors.push(FieldError::new(
+            "questions",
+            "questions_required",
+            "A quiz needs at least one question",
+        ));
+    }
+    if quiz.questions.len() > MAX_QUESTIONS {
+        errors.push(FieldError::new(
+            "questions",
+            "too_many_questions",
+            format!(
+                "A quiz may have at most {} questions, got {}",
+                MAX_QUESTIONS,
+                quiz.questions.len()
+            ),
+        ));
+    }
+
+    let mut first_invalid = None;
+    for (index, question) in quiz.questions.iter().enumerate() {
+        let problems = question.problems();
+        if !problems.is_empty() {
+            first_invalid.get_or_insert(index);
+        }
+        errors.extend(problems.into_iter().map(|problem| {
+            FieldError::new(
+                format!("questions[{}].{}", index, problem.field),
+                problem.code,
+                format!("Invalid question {}: {}", index, problem.message),
+            )
+        }));
+    }
+
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let mut body = json!({ "error": "Invalid quiz", "code": "invalid_quiz", "errors": errors });
+    if let Some(index) = first_invalid {
+        body["question_index"] = json!(index);
+    }
+    Err(body)
+}
+
+/// Problems with the quiz-level fields shared by create, `PUT` and `PATCH`
+fn quiz_metadata_errors(title: &str, jlpt_level: Option<&str>) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    if title.trim().is_empty() {
+        errors.push(FieldError::new(
+            "title",
+            "title_required",
+            "title must not be empty",
+        ));
+    }
+    let title_length = title.chars().count();
+    if title_length > MAX_TITLE_CHARS {
+        errors.push(FieldError::new(
+            "title",
+            "title_too_long",
+            format!(
+                "title must be at most {} characters, got {}",
+                MAX_TITLE_CHARS, title_length
+            ),
+        ));
+    }
+
+    if let Some(level) = jlpt_level {
+        if !JLPT_LEVELS.contains(&level) {
+            errors.push(FieldError::new(
+                "jlpt_level",
+                "invalid_jlpt_level",
+                format!(
+                    "Invalid jlpt_level '{}', expected one of {}",
+                    level,
+                    JLPT_LEVELS.join(", ")
+                ),
+            ));
+        }
+    }
+    errors
+}
+
+/// Validate the quiz-level fields of `PATCH` and merge, stopping at the
+/// first problem (an over-long title also reports `title_length`)
+fn validate_quiz_metadata(title: &str, jlpt_level: Option<&str>) -> Result<(), JsonValue> {
+    let Some(first) = quiz_metadata_errors(title, jlpt_level).into_iter().next() else {
+        return Ok(());
+    };
+    let mut body = json!({ "error": first.message, "code": first.code });
+    let title_length = title.chars().count();
+    if first.field == "title" && title_length > MAX_TITLE_CHARS {
+        body["title_length"] = json!(title_length);
+    }
+    Err(body)
+}
+
+/// Page size used by `list_quizzes` when no `limit` is given
+const DEFAULT_PAGE_LIMIT: i64 = 20;
+/// Largest page size `list_quizzes` will return
+const MAX_PAGE_LIMIT: i64 = 100;
+
+/// Escape `%`, `_` and `\` so user input is matched literally by `LIKE ... ESCAPE '\'`
+fn escape_like(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Parse a `created_after`/`created_before` bound (RFC 3339, any offset) into
+/// the UTC `YYYY-MM-DD HH:MM:SS` form stored in `created_at`, so the two
+/// compare as strings. On a malformed value returns the 400 body.
+fn created_at_bound(name: &str, value: &str) -> Result<String, JsonValue> {
+    DateTime::parse_from_rfc3339(value.trim())
+        .map(|dt| {
+            dt.with_timezone(&Utc)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .map_err(|_| {
+            json!({
+                "error": format!(
+                    "Invalid {} '{}', expected an RFC 3339 time such as 2024-01-31T00:00:00Z",
+                    name, value
+                ),
+                "code": "invalid_date"
+            })
+        })
+}
+
+/// Build the `WHERE` clause (and its bind values, in order) selecting the
+/// non-deleted quizzes that match the `q`/`level`/`tag`/`include_drafts`/
+/// `favorites_only`/`created_after`/`created_before` filters of a listing.
+/// Drafts are left out unless `include_drafts=true`. The clause only uses
+/// `quizzes` columns, unqualified. On an invalid filter returns the 400 body.
+fn quiz_filters(query: &ListQuizzesQuery) -> Result<(String, Vec<String>), JsonValue> {
+    let mut where_clauses: Vec<&str> = vec!["deleted_at IS NULL"];
+    let mut binds: Vec<String> = Vec::new();
+
+    if !query.include_drafts.unwrap_or(false) {
+        where_clauses.push("status = ?");
+        binds.push(QuizStatus::Published.as_str().to_string());
+    }
+
+    // SQLite's LIKE only folds ASCII case, so the query is matched against
+    // `title_key` (NFKC, lowercased) in the same form: `ｊｌｐｔ` and `Ä`
+    // find `JLPT` and `ä` as well.
+    if let Some(q) = query
+        .q
+        .as_deref()
+        .map(tools::title_key)
+        .filter(|q| !q.is_empty())
+    {
+        where_clauses.push("title_key LIKE '%' || ? || '%' ESCAPE '\\'");
+        binds.push(escape_like(&q));
+    }
+
+    if let Some(level) = query.level.as_deref().filter(|l| !l.is_empty()) {
+        let level = level.to_uppercase();
+        if !JLPT_LEVELS.contains(&level.as_str()) {
+            return Err(json!({
+                "error": format!(
+                    "Invalid level '{}', expected one of {}",
+                    level,
+                    JLPT_LEVELS.join(", ")
+                ),
+                "code": "invalid_jlpt_level"
+            }));
+        }
+        where_clauses.push("jlpt_level = ?");
+        binds.push(level);
+    }
+
+    if let Some(tag) = query.tag.as_deref().map(|t| t.trim().to_lowercase()) {
+        if !tag.is_empty() {
+            where_clauses.push(
+                "id IN (SELECT qt.quiz_id FROM quiz_tags qt JOIN tags t ON t.id = qt.tag_id WHERE t.name = ?)",
+            );
+            binds.push(tag);
+        }
+    }
+
+    if query.favorites_only.unwrap_or(false) {
+        where_clauses.push("favorite = 1");
+    }
+
+    let created_after = query
+        .created_after
+        .as_deref()
+        .map(|v| created_at_bound("created_after", v))
+        .transpose()?;
+    let created_before = query
+        .created_before
+        .as_deref()
+        .map(|v| created_at_bound("created_before", v))
+        .transpose()?;
+    if let (Some(after), Some(before)) = (&created_after, &created_before) {
+        if after > before {
+            return Err(json!({
+                "error": "created_after must not be later than created_before",
+                "code": "invalid_date_range"
+            }));
+        }
+    }
+    if let Some(after) = created_after {
+        where_clauses.push("created_at >= ?");
+        binds.push(after);
+    }
+    if let Some(before) = created_before {
+        where_clauses.push("created_at < ?");
+        binds.push(before);
+    }
+
+    Ok((format!("WHERE {}", where_clauses.join(" AND ")), binds))
+}
+
+/// List quizzes, one page at a time
+///
+/// Accepts optional `limit` (default 20, capped at 100) and `offset` query
+/// parameters and responds with `{ quizzes, total, limit, offset }`.
+/// Optional filters narrow the set that `total` counts: `q` (title substring,
+/// case-insensitive), `level` (JLPT level), `tag` (tag name) and
+/// `created_after`/`created_before` (RFC 3339; either bound may be left out).
+/// Drafts are only listed with `include_drafts=true`, and only favorites with
+/// `favorites_only=true`.
+/// Questions for every listed quiz are loaded with one extra query and grouped
+/// by `quiz_id` in memory, instead of issuing one query per quiz.
+#[utoipa::path(
+    get,
+    path = "/api/quizzes",
+    params(ListQuizzesQuery),
+    responses(
+        (status = 200, description = "One page of quizzes, newest first", body = QuizPage),
+        (status = 400, description = "Invalid filter", body = ErrorResponse),
+    )
+)]
+pub async fn list_quizzes(
+    pool: web::Data<SqlitePool>,
+    query: web::Query<ListQuizzesQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_LIMIT)
+        .clamp(1, MAX_PAGE_LIMIT);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let (where_sql, binds) = quiz_filters(&query).map_err(ApiError::BadRequest)?;
+
+    let count_sql = format!("SELECT COUNT(*) FROM quizzes {}", where_sql);
+    let mut count_q = sqlx::query_scalar(&count_sql);
+    for b in &binds {
+        count_q = count_q.bind(b);
+    }
+    let total: i64 = count_q
+        .fetch_one(pool)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to fetch quizzes", e))?;
+
+    let list_sql = format!(
+        "SELECT {} FROM quizzes {} ORDER BY created_at DESC, id DESC LIMIT ? OFFSET ?",
+        QUIZ_COLUMNS, where_sql
+    );
+    let mut list_q = sqlx::query(&list_sql);
+    for b in &binds {
+        list_q = list_q.bind(b);
+    }
+    let rows = list_q
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to fetch quizzes", e))?;
+
+    let quiz_ids: Vec<i64> = rows
+        .iter()
+        .map(|row| row.try_get("id").unwrap_or(0))
+        .collect();
+
+    let mut questions_by_quiz = fetch_questions_for(pool, &quiz_ids)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to fetch questions", e))?;
+
+    let mut tags_by_quiz = fetch_tags(pool, &quiz_ids)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to fetch quizzes", e))?;
+
+    let quizzes: Vec<Quiz> = rows
+        .iter()
+        .zip(quiz_ids)
+        .map(|(row, quiz_id)| {
+            quiz_from_row(
+                row,
+                tags_by_quiz.remove(&quiz_id).unwrap_or_default(),
+                questions_by_quiz.remove(&quiz_id).unwrap_or_default(),
+            )
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "quizzes": quizzes,
+        "total": total,
+        "limit": limit,
+        "offset": offset
+    })))
+}
+
+/// List quizzes without their questions, for index pages
+///
+/// Takes the same paging and filter parameters as `list_quizzes` and responds
+/// with `{ quizzes, total, limit, offset }`, where each quiz is just `id`,
+/// `title`, `description` and `question_count`. Question counts come from the
+/// `QuestionCounts` cache when present; the rest are counted with one query.
+pub async fn quiz_summaries(
+    pool: web::Data<SqlitePool>,
+    question_counts: web::Data<QuestionCounts>,
+    query: web::Query<ListQuizzesQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_LIMIT)
+        .clamp(1, MAX_PAGE_LIMIT);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let (where_sql, binds) = quiz_filters(&query).map_err(ApiError::BadRequest)?;
+
+    let count_sql = format!("SELECT COUNT(*) FROM quizzes {}", where_sql);
+    let mut count_q = sqlx::query_scalar(&count_sql);
+    for b in &binds {
+        count_q = count_q.bind(b);
+    }
+    let total: i64 = count_q
+        .fetch_one(pool)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to fetch quizzes", e))?;
+
+    let list_sql = format!(
+        "SELECT id, title, description FROM quizzes {} ORDER BY created_at DESC, id DESC LIMIT ? OFFSET ?",
+        where_sql
+    );
+    let mut list_q = sqlx::query(&list_sql);
+    for b in &binds {
+        list_q = list_q.bind(b);
+    }
+    let rows = list_q
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to fetch quizzes", e))?;
+
+    let quiz_ids: Vec<i64> = rows
+        .iter()
+        .map(|row| row.try_get("id").unwrap_or(0))
+        .collect();
+
+    // Count only the questions of this page's quizzes that aren't cached
+    let (mut counts, generation) = question_counts.get(&quiz_ids);
+    let missing: Vec<i64> = quiz_ids
+        .iter()
+        .copied()
+        .filter(|id| !counts.contains_key(id))
+        .collect();
+    if !missing.is_empty() {
+        let count_sql = format!(
+            "SELECT quiz_id, COUNT(*) FROM quiz_questions WHERE quiz_id IN ({}) GROUP BY quiz_id",
+            vec!["?"; missing.len()].join(", ")
+        );
+        let mut count_q = sqlx::query_as::<_, (i64, i64)>(&count_sql);
+        for id in &missing {
+            count_q = count_q.bind(id);
+        }
+        let found: HashMap<i64, i64> = count_q
+            .fetch_all(pool)
+            .await
+            .map_err(|e| ApiError::internal_from("Failed to fetch quizzes", e))?
+            .into_iter()
+            .collect();
+        let fetched: HashMap<i64, usize> = missing
+            .iter()
+            .map(|id| (*id, found.get(id).copied().unwrap_or(0) as usize))
+            .collect();
+        question_counts.fill(generation, &fetched);
+        counts.extend(fetched);
+    }
+
+    let quizzes: Vec<JsonValue> = rows
+        .iter()
+        .zip(&quiz_ids)
+        .map(|(r, id)| {
+            json!({
+                "id": id,
+                "title": r.try_get::<String, _>("title").unwrap_or_default(),
+                "description": r.try_get::<Option<String>, _>("description").ok().flatten(),
+                "question_count": counts.get(id).copied().unwrap_or(0),
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "quizzes": quizzes,
+        "total": total,
+        "limit": limit,
+        "offset": offset
+    })))
+}
+
+/// ETag for a serialized quiz: the hex SHA-256 of the response body.
+///
+/// It is a hash of the body rather than `updated_at`/`version`: `updated_at`
+/// only has one-second resolution and `version` is only bumped by full
+/// updates, while the body changes with every edit. It is weak because the
+/// `Compress` middleware may encode the body after it is hashed, and the
+/// encoded bytes differ from the ones hashed.
+fn quiz_etag(body: &[u8]) -> header::EntityTag {
+    header::EntityTag::new_weak(format!("{:x}", Sha256::digest(body)))
+}
+
+/// Parse a `?fields=` list into the question fields to keep, always with
+/// `id`; 400 for a name not in `QUESTION_FIELDS`
+fn question_fields(list: &str) -> Result<HashSet<&str>, ApiError> {
+    let mut fields: HashSet<&str> = HashSet::from(["id"]);
+    for field in list.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+        if !QUESTION_FIELDS.contains(&field) {
+            return Err(ApiError::bad_request(
+                "unknown_field",
+                format!(
+                    "Unknown field '{}', expected some of {}",
+                    field,
+                    QUESTION_FIELDS.join(", ")
+                ),
+            ));
+        }
+        fields.insert(field);
+    }
+    Ok(fields)
+}
+
+/// Get a single quiz
+///
+/// With `?fields=text,options` each question only has the listed fields
+/// (and its `id`); the quiz-level fields are unaffected.
+///
+/// With `?shuffle=true` each question's options come back in random order,
+/// with `correct_answer` remapped to the new position of the correct option.
+/// With `?render=html` the description (stored as Markdown) is returned as
+/// sanitized HTML.
+#[utoipa::path(
+    get,
+    path = "/api/quizzes/{id}",
+    params(("id" = i32, Path, description = "Quiz id"), GetQuizQuery),
+    responses(
+        (status = 200, description = "The quiz with its questions", body = Quiz),
+        (status = 304, description = "Unchanged since the ETag sent in If-None-Match"),
+        (status = 404, description = "No such quiz", body = ErrorResponse),
+    )
+)]
+pub async fn get_quiz(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    quiz_id: web::Path<i32>,
+    query: web::Query<GetQuizQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+
+    let render_html = match query.render.as_deref() {
+        None => false,
+        Some("html") => true,
+        Some(other) => {
+            return Err(ApiError::bad_request(
+                "unknown_render",
+                format!("Unknown render '{}', expected html", other),
+            ))
+        }
+    };
+    let fields = query.fields.as_deref().map(question_fields).transpose()?;
+
+    match fetch_quiz(pool, id).await {
+        Ok(Some(mut quiz)) => {
+            if render_html {
+                quiz.description = quiz.description.as_deref().map(export::markdown_to_html);
+            }
+            let shuffle = query.shuffle.unwrap_or(false);
+            if shuffle {
+                let mut rng = rand::thread_rng();
+                for question in &mut quiz.questions {
+                    question.shuffle_options(&mut rng);
+                }
+            }
+
+            let body = match &fields {
+                Some(fields) => serde_json::to_value(&quiz).and_then(|mut value| {
+                    let questions = value["questions"].as_array_mut().into_iter().flatten();
+                    for question in questions.filter_map(JsonValue::as_object_mut) {
+                        question.retain(|name, _| fields.contains(name.as_str()));
+                    }
+                    serde_json::to_vec(&value)
+                }),
+                None => serde_json::to_vec(&quiz),
+            }
+            .map_err(|e| ApiError::internal_from("Failed to serialize quiz", e))?;
+            if shuffle {
+                // Every shuffled response differs, so there is nothing to cache
+                return Ok(HttpResponse::Ok()
+                    .content_type("application/json")
+                    .body(body));
+            }
+
+            let etag = quiz_etag(&body);
+            if let Ok(header::IfNoneMatch::Items(tags)) = header::IfNoneMatch::parse(&req) {
+                if tags.iter().any(|tag| tag.weak_eq(&etag)) {
+                    return Ok(HttpResponse::NotModified()
+                        .insert_header(header::ETag(etag))
+                        .finish());
+                }
+            }
+            Ok(HttpResponse::Ok()
+                .insert_header(header::ETag(etag))
+                .content_type("application/json")
+                .body(body))
+        }
+        Ok(None) => Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+        Err(e) => Err(ApiError::internal_from("Failed to fetch questions", e)),
+    }
+}
+
+/// Get a quiz with only the questions at or below `?max_difficulty` (1-5)
+///
+/// Without the parameter every question is returned.
+pub async fn practice_quiz(
+    pool: web::Data<SqlitePool>,
+    quiz_id: web::Path<i32>,
+    query: web::Query<PracticeQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+
+    if let Some(max) = query.max_difficulty {
+        if !DIFFICULTY_RANGE.contains(&max) {
+            return Err(ApiError::bad_request(
+                "invalid_max_difficulty",
+                format!(
+                    "max_difficulty must be between {} and {}",
+                    DIFFICULTY_RANGE.start(),
+                    DIFFICULTY_RANGE.end()
+                ),
+            ));
+        }
+    }
+
+    match fetch_quiz(pool, id).await {
+        Ok(Some(mut quiz)) => {
+            if let Some(max) = query.max_difficulty {
+                quiz.questions.retain(|q| q.difficulty <= max);
+            }
+            Ok(HttpResponse::Ok().json(quiz))
+        }
+        Ok(None) => Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+        Err(e) => Err(ApiError::internal_from("Failed to fetch questions", e)),
+    }
+}
+
+/// Questions drawn per session when `?count` is omitted
+const DEFAULT_RANDOM_COUNT: i64 = 10;
+
+/// Get a quiz with `?count` questions picked at random from its pool
+///
+/// If the quiz has fewer questions than requested, all of them are returned
+/// (in random order).
+pub async fn random_quiz(
+    pool: web::Data<SqlitePool>,
+    quiz_id: web::Path<i32>,
+    query: web::Query<RandomQuizQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+
+    let count = query.count.unwrap_or(DEFAULT_RANDOM_COUNT);
+    if count < 1 {
+        return Err(ApiError::bad_request(
+            "invalid_count",
+            "count must be at least 1",
+        ));
+    }
+
+    let mut quiz = fetch_quiz(pool, id)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to fetch questions", e))?
+        .ok_or_else(|| ApiError::not_found("quiz_not_found", "Quiz not found"))?;
+
+    match fetch_random_questions(pool, id, count).await {
+        Ok(questions) => {
+            quiz.questions = questions;
+            Ok(HttpResponse::Ok().json(quiz))
+        }
+        Err(e) => Err(ApiError::internal_from("Failed to fetch questions", e)),
+    }
+}
+
+/// Which of `count` quizzes (ordered by id) is the quiz of the day `date`.
+/// The day number goes through the SplitMix64 finalizer so consecutive days
+/// jump around the list instead of walking it in order.
+fn daily_index(date: NaiveDate, count: u64) -> u64 {
+    let mut x = (date.num_days_from_ce() as u64).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (x ^ (x >> 31)) % count
+}
+
+/// The quiz of the day: one published quiz picked from the current UTC date,
+/// so every request on the same day gets the same quiz and the pick changes
+/// at midnight UTC. 404 when nothing is published.
+pub async fn daily_quiz(pool: web::Data<SqlitePool>) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+
+    let published: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM quizzes WHERE deleted_at IS NULL AND status = ?")
+            .bind(QuizStatus::Published.as_str())
+            .fetch_one(pool)
+            .await
+            .map_err(|e| ApiError::internal_from("Failed to fetch quiz", e))?;
+    if published == 0 {
+        return Err(ApiError::not_found("quiz_not_found", "Quiz not found"));
+    }
+
+    let offset = daily_index(Utc::now().date_naive(), published as u64);
+    let id: i64 = sqlx::query_scalar(
+        "SELECT id FROM quizzes WHERE deleted_at IS NULL AND status = ? ORDER BY id LIMIT 1 OFFSET ?",
+    )
+    .bind(QuizStatus::Published.as_str())
+    .bind(offset as i64)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| ApiError::internal_from("Failed to fetch quiz", e))?
+    // A quiz unpublished between the two queries
+    .ok_or_else(|| ApiError::not_found("quiz_not_found", "Quiz not found"))?;
+
+    match fetch_quiz(pool, id).await {
+        Ok(Some(quiz)) => Ok(HttpResponse::Ok().json(quiz)),
+        Ok(None) => Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+        Err(e) => Err(ApiError::internal_from("Failed to fetch questions", e)),
+    }
+}
+
+/// Every distinct option string used across a quiz's questions, for
+/// building new questions with consistent distractors
+///
+/// Responds with `{ distractors }` in first-use order (by question
+/// position). With `?exclude_correct=true` an option is only taken from
+/// questions where it is not the correct answer.
+pub async fn quiz_distractors(
+    pool: web::Data<SqlitePool>,
+    quiz_id: web::Path<i32>,
+    query: web::Query<DistractorsQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+    let exclude_correct = query.exclude_correct.unwrap_or(false);
+
+    let quiz = fetch_quiz(pool, id)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to fetch questions", e))?
+        .ok_or_else(|| ApiError::not_found("quiz_not_found", "Quiz not found"))?;
+
+    let mut seen = HashSet::new();
+    let mut distractors: Vec<&str> = Vec::new();
+    for question in &quiz.questions {
+        for (index, option) in question.options.iter().enumerate() {
+            if exclude_correct && question.correct_answers.contains(&(index as i32)) {
+                continue;
+            }
+            if seen.insert(option.as_str()) {
+                distractors.push(option);
+            }
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(json!({ "distractors": distractors })))
+}
+
+/// Request header carrying a client-chosen key that makes `create_quiz` safe
+/// to retry
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// Longest accepted `Idempotency-Key`
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// Quiz created earlier with this idempotency key, if within the last 24
+/// hours
+async fn idempotent_quiz(pool: &SqlitePool, key: &str) -> Result<Option<i64>, sqlx::Error> {
+    sqlx::query("DELETE FROM idempotency_keys WHERE created_at < datetime('now', '-24 hours')")
+        .execute(pool)
+        .await?;
+    sqlx::query_scalar("SELECT quiz_id FROM idempotency_keys WHERE key = ?")
+        .bind(key)
+        .fetch_optional(pool)
+        .await
+}
+
+/// The 200 answering a create retried with a known `Idempotency-Key`. The
+/// owner token was only returned by the first response.
+fn replayed_create(id: i64) -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "id": id,
+        "message": "Quiz already created",
+        "owner_token": null,
+        "warnings": []
+    }))
+}
+
+/// Header carrying the owner token(s) handed out by `create_quiz`
+const OWNER_TOKEN_HEADER: &str = "x-owner-token";
+
+/// Fail with 403 unless the request's `X-Owner-Token` header holds the owner
+/// token of every quiz in `ids` that has one. Several tokens may be given,
+/// comma-separated, for requests covering several quizzes.
+///
+/// Called by every handler that changes a quiz or its questions (starring
+/// aside, which doesn't change the quiz). Soft-deleted quizzes are checked
+/// too, so only the owner can restore one. Quizzes created before tokens
+/// existed have none and stay open to anyone, and ids that don't exist pass
+/// so the handler answers 404 as it would otherwise.
+async fn require_owner(pool: &SqlitePool, req: &HttpRequest, ids: &[i64]) -> Result<(), ApiError> {
+    let sql = format!(
+        "SELECT owner_token_hash FROM quizzes WHERE owner_token_hash IS NOT NULL AND id IN ({})",
+        vec!["?"; ids.len()].join(", ")
+    );
+    let mut q = sqlx::query_scalar::<_, String>(&sql);
+    for id in ids {
+        q = q.bind(id);
+    }
+    let owned = q
+        .fetch_all(pool)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to check owner token", e))?;
+    if owned.is_empty() {
+        return Ok(());
+    }
+
+    let given: HashSet<String> = req
+        .headers()
+        .get(OWNER_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|tokens| {
+            tokens
+                .split(',')
+                .map(str::trim)
+                .filter(|token| !token.is_empty())
+                .map(hash_owner_token)
+                .collect()
+        })
+        .unwrap_or_default();
+    if given.is_empty() {
+        return Err(ApiError::forbidden(
+            "owner_token_required",
+            "X-Owner-Token is required to change this quiz",
+        ));
+    }
+    if !owned.iter().all(|hash| given.contains(hash)) {
+        return Err(ApiError::forbidden(
+            "owner_token_mismatch",
+            "X-Owner-Token does not match the quiz",
+        ));
+    }
+    Ok(())
+}
+
+/// Create a quiz (and its questions)
+///
+/// The response's `owner_token` is needed (as `X-Owner-Token`) to change the
+/// quiz later; it is only ever returned here.
+///
+/// With an `Idempotency-Key` header, a retry carrying the same key within 24
+/// hours returns the id of the quiz created the first time (with 200
+/// instead of 201) rather than creating a duplicate. Its `owner_token` is
+/// null: the token is stored only as a hash, so a client retrying must keep
+/// it from the first response that reached it.
+///
+/// With `?unique_title=true`, a title already used by a non-deleted quiz
+/// (compared case- and width-insensitively) is rejected with 409 and the
+/// `existing_id` of that quiz.
+#[utoipa::path(
+    post,
+    path = "/api/quizzes",
+    request_body = CreateQuizRequest,
+    params(
+        CreateQuizQuery,
+        ("Idempotency-Key" = Option<String>, Header, description = "Makes retries safe"),
+    ),
+    responses(
+        (status = 201, description = "Quiz created", body = QuizCreated),
+        (status = 200, description = "Retry of an earlier create with the same Idempotency-Key", body = QuizCreated),
+        (status = 400, description = "Invalid quiz", body = ErrorResponse),
+        (status = 409, description = "unique_title was set and the title is taken", body = ErrorResponse),
+    )
+)]
+pub async fn create_quiz(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    kanji_readings: web::Data<KanjiReadings>,
+    query: web::Query<CreateQuizQuery>,
+    mut quiz_data: web::Json<CreateQuizRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    quiz_data.normalize();
+
+    validate_quiz_request(&quiz_data).map_err(ApiError::BadRequest)?;
+
+    let idempotency_key = match req.headers().get(IDEMPOTENCY_KEY_HEADER) {
+        Some(value) => match value.to_str().map(str::trim) {
+            Ok(key) if !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LEN => {
+                Some(key.to_string())
+            }
+            _ => {
+                return Err(ApiError::bad_request(
+                    "invalid_idempotency_key",
+                    format!(
+                        "Idempotency-Key must be 1 to {} visible ASCII characters",
+                        MAX_IDEMPOTENCY_KEY_LEN
+                    ),
+                ))
+            }
+        },
+        None => None,
+    };
+
+    if let Some(key) = &idempotency_key {
+        match idempotent_quiz(pool, key).await {
+            Ok(Some(id)) => return Ok(replayed_create(id)),
+            Ok(None) => {}
+            Err(e) => return Err(ApiError::internal_from("Failed to create quiz", e)),
+        }
+    }
+
+    // The quiz row and all of its questions are written in a single transaction
+    // so a failing question insert never leaves a half-created quiz behind.
+    // Dropping `tx` without committing rolls everything back.
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to create quiz", e))?;
+
+    // The insert comes first so the transaction holds SQLite's write lock
+    // before it reads, as BEGIN IMMEDIATE would: a concurrent create with the
+    // same title waits for this one to finish and then sees its quiz. Read
+    // first, both would pass the check and the loser would fail to upgrade
+    // its lock. A duplicate found afterwards rolls the insert back.
+    let (quiz_id, owner_token) = match insert_quiz(&mut tx, &quiz_data).await {
+        Ok((id, owner_token)) => (id as i32, owner_token),
+        Err(msg) => return Err(ApiError::internal(msg)),
+    };
+
+    if query.unique_title.unwrap_or(false) {
+        let existing = queries::quiz_id_with_title(&mut *tx, &quiz_data.title)
+            .await
+            .map_err(|e| ApiError::internal_from("Failed to create quiz", e))?;
+        // the lowest id with the title, so an older quiz is found before ours
+        if let Some(existing_id) = existing.filter(|&existing| existing != quiz_id as i64) {
+            return Err(ApiError::Conflict(json!({
+                "error": "A quiz with this title already exists",
+                "code": "duplicate_title",
+                "existing_id": existing_id
+            })));
+        }
+    }
+
+    if let Some(key) = &idempotency_key {
+        let recorded = sqlx::query("INSERT INTO idempotency_keys (key, quiz_id) VALUES (?, ?)")
+            .bind(key)
+            .bind(quiz_id)
+            .execute(&mut *tx)
+            .await;
+        if let Err(insert_error) = recorded {
+            // A concurrent request with the same key won the race; discard
+            // this quiz and answer with that one
+            drop(tx);
+            return match idempotent_quiz(pool, key).await {
+                Ok(Some(id)) => Ok(replayed_create(id)),
+                Ok(None) => Err(ApiError::internal_from(
+                    "Failed to create quiz",
+                    insert_error,
+                )),
+                Err(e) => Err(ApiError::internal_from("Failed to create quiz", e)),
+            };
+        }
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to create quiz", e))?;
+
+    let warnings = reading_warnings(&kanji_readings, &quiz_data.questions);
+    Ok(HttpResponse::Created().json(json!({
+        "id": quiz_id,
+        "message": "Quiz created successfully",
+        "owner_token": owner_token,
+        "warnings": warnings
+    })))
+}
+
+/// Questions whose reading doesn't fit the kanji of their text (see
+/// `KanjiReadings::check`), as `questions[i].reading` field errors; empty
+/// when no readings file is configured
+fn reading_warnings(
+    kanji_readings: &KanjiReadings,
+    questions: &[QuestionInput],
+) -> Vec<FieldError> {
+    questions
+        .iter()
+        .enumerate()
+        .filter_map(|(index, question)| {
+            let reading = question.reading.as_deref()?;
+            kanji_readings
+                .check(&question.text, reading)
+                .map(|message| {
+                    FieldError::new(
+                        format!("questions[{}].reading", index),
+                        "unexpected_reading",
+                        message,
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Create many quizzes at once from a JSON array of create payloads
+///
+/// The whole batch is validated and then inserted in a single transaction:
+/// if any item fails, nothing is created and the 400 response carries the
+/// `index` of the failing item. Responds 201 with the new `ids` and their
+/// `owner_tokens`, in the same order.
+pub async fn import_quizzes(
+    pool: web::Data<SqlitePool>,
+    mut quizzes: web::Json<Vec<CreateQuizRequest>>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    quizzes.iter_mut().for_each(CreateQuizRequest::normalize);
+
+    for (index, quiz) in quizzes.iter().enumerate() {
+        if let Err(mut body) = validate_quiz_request(quiz) {
+            body["index"] = json!(index);
+            return Err(ApiError::BadRequest(body));
+        }
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to import quizzes", e))?;
+
+    let mut ids = Vec::with_capacity(quizzes.len());
+    let mut owner_tokens = Vec::with_capacity(quizzes.len());
+    for (index, quiz) in quizzes.iter().enumerate() {
+        match insert_quiz(&mut tx, quiz).await {
+            Ok((id, owner_token)) => {
+                ids.push(id);
+                owner_tokens.push(owner_token);
+            }
+            Err(msg) => {
+                return Err(ApiError::BadRequest(
+                    json!({ "error": msg, "code": "import_failed", "index": index }),
+                ))
+            }
+        }
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to import quizzes", e))?;
+
+    Ok(HttpResponse::Created().json(json!({ "ids": ids, "owner_tokens": owner_tokens })))
+}
+
+/// Create quizzes from an NDJSON body in the format of `GET /api/export/all`
+/// (one quiz per line; blank lines are skipped), restoring a backup.
+///
+/// The body is read as it arrives (see `import::NdjsonLines`) and each line
+/// is created in its own transaction, so only one line is in memory at a
+/// time and a bad line doesn't undo the others. A line may be up to
+/// `MAX_BODY_BYTES` long. Responds with `{ imported, created, failed }`,
+/// where `created` lists each new quiz as `{ line, id, owner_token }` and
+/// `failed` lists each bad line as `{ line, error }` (plus `errors` when
+/// validation found several problems).
+pub async fn import_all(
+    pool: web::Data<SqlitePool>,
+    config: web::Data<Config>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let mut lines = import::NdjsonLines::new(config.max_body_bytes);
+    let mut created: Vec<JsonValue> = Vec::new();
+    let mut failed: Vec<JsonValue> = Vec::new();
+
+    while let Some(chunk) = payload.next().await {
+        let Ok(chunk) = chunk else {
+            return Err(ApiError::BadRequest(json!({
+                "error": "Failed to read request body",
+                "code": "unreadable_body",
+                "imported": created.len(),
+                "created": created,
+                "failed": failed
+            })));
+        };
+        for (number, line) in lines.push(&chunk) {
+            match import_ndjson_line(pool, line).await {
+                Ok((id, owner_token)) => {
+                    created.push(json!({ "line": number, "id": id, "owner_token": owner_token }))
+                }
+                Err(mut body) => {
+                    body["line"] = json!(number);
+                    failed.push(body);
+                }
+            }
+        }
+    }
+    if let Some((number, line)) = lines.finish() {
+        match import_ndjson_line(pool, line).await {
+            Ok((id, owner_token)) => {
+                created.push(json!({ "line": number, "id": id, "owner_token": owner_token }))
+            }
+            Err(mut body) => {
+                body["line"] = json!(number);
+                failed.push(body);
+            }
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "imported": created.len(),
+        "created": created,
+        "failed": failed
+    })))
+}
+
+/// Create the quiz on one line of `import_all` in its own transaction,
+/// returning its id and owner token; on failure returns the error body for
+/// its `failed` entry
+async fn import_ndjson_line(
+    pool: &SqlitePool,
+    line: Result<Vec<u8>, String>,
+) -> Result<(i64, String), JsonValue> {
+    let bytes = line.map_err(|error| json!({ "error": error, "code": "line_too_long" }))?;
+    let imported = import_line(&bytes)?;
+    insert_imported_quiz(pool, &imported)
+        .await
+        .map_err(|msg| json!({ "error": msg, "code": "import_failed" }))
+}
+
+/// Parse, normalize and validate one line of `import_all`; on failure returns
+/// the error body for its `failed` entry
+fn import_line(bytes: &[u8]) -> Result<ImportedQuiz, JsonValue> {
+    let mut imported: ImportedQuiz = serde_json::from_slice(bytes)
+        .map_err(|e| json!({ "error": format!("Invalid JSON: {}", e), "code": "invalid_json" }))?;
+    imported.quiz.normalize();
+    validate_quiz_request(&imported.quiz)?;
+    Ok(imported)
+}
+
+/// Create one quiz of `import_all` in its own transaction, returning its id
+/// and owner token
+async fn insert_imported_quiz(
+    pool: &SqlitePool,
+    imported: &ImportedQuiz,
+) -> Result<(i64, String), &'static str> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| log_error("Failed to import quizzes", e))?;
+    let (id, owner_token) = insert_quiz(&mut tx, &imported.quiz).await?;
+    sqlx::query("UPDATE quizzes SET status = ?, favorite = ? WHERE id = ?")
+        .bind(imported.status.as_str())
+        .bind(imported.favorite)
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| log_error("Failed to import quizzes", e))?;
+    tx.commit()
+        .await
+        .map_err(|e| log_error("Failed to import quizzes", e))?;
+    Ok((id, owner_token))
+}
+
+/// Combine several quizzes into a new one titled `title`
+///
+/// The new quiz holds every question of `source_ids`, quiz by quiz in the
+/// order given, and the union of their tags; it keeps a JLPT level only if
+/// all sources share it. Every source must exist before anything is written.
+/// With `?delete_sources=true` the sources are soft-deleted in the same
+/// transaction, which needs their owner tokens (comma-separated in
+/// `X-Owner-Token`, see `require_owner`). Responds 201 with the new id and
+/// its `owner_token`.
+pub async fn merge_quizzes(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    query: web::Query<MergeQuizzesQuery>,
+    body: web::Json<MergeQuizzesRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let body = body.into_inner();
+    let title = body.title.trim().to_string();
+
+    validate_quiz_metadata(&title, None).map_err(ApiError::BadRequest)?;
+    if body.source_ids.len() < 2 {
+        return Err(ApiError::bad_request(
+            "too_few_sources",
+            "source_ids must list at least two quizzes",
+        ));
+    }
+    for (index, id) in body.source_ids.iter().enumerate() {
+        if body.source_ids[..index].contains(id) {
+            return Err(ApiError::bad_request(
+                "duplicate_source",
+                format!("source_ids lists quiz {} more than once", id),
+            ));
+        }
+    }
+    if query.delete_sources.unwrap_or(false) {
+        require_owner(pool, &req, &body.source_ids).await?;
+    }
+
+    let mut sources = Vec::with_capacity(body.source_ids.len());
+    let mut missing = Vec::new();
+    for &id in &body.source_ids {
+        match fetch_quiz(pool, id)
+            .await
+            .map_err(|e| ApiError::internal_from("Failed to fetch quizzes", e))?
+        {
+            Some(quiz) => sources.push(quiz),
+            None => missing.push(id),
+        }
+    }
+    if !missing.is_empty() {
+        return Err(ApiError::BadRequest(json!({
+            "error": "Some source quizzes do not exist",
+            "code": "source_quizzes_missing",
+            "missing_ids": missing
+        })));
+    }
+
+    let question_count: usize = sources.iter().map(|q| q.questions.len()).sum();
+    if question_count > MAX_QUESTIONS {
+        return Err(ApiError::bad_request(
+            "too_many_questions",
+            format!(
+                "A quiz may have at most {} questions, the sources have {}",
+                MAX_QUESTIONS, question_count
+            ),
+        ));
+    }
+
+    let first_level = sources[0].jlpt_level.clone();
+    let jlpt_level =
+        first_level.filter(|level| sources.iter().all(|q| q.jlpt_level.as_ref() == Some(level)));
+    let mut tags: Vec<String> = Vec::new();
+    for tag in sources.iter().flat_map(|q| &q.tags) {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+    let merged = CreateQuizRequest {
+        title,
+        description: None,
+        jlpt_level,
+        tags,
+        version: None,
+        questions: sources
+            .into_iter()
+            .flat_map(|q| q.questions)
+            .map(QuestionInput::from)
+            .collect(),
+    };
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to merge quizzes", e))?;
+
+    let (new_id, owner_token) = insert_quiz(&mut tx, &merged)
+        .await
+        .map_err(ApiError::internal)?;
+
+    if query.delete_sources.unwrap_or(false) {
+        let placeholders = vec!["?"; body.source_ids.len()].join(", ");
+        let sql = format!(
+            "UPDATE quizzes SET deleted_at = datetime('now') WHERE deleted_at IS NULL AND id IN ({})",
+            placeholders
+        );
+        let mut delete = sqlx::query(&sql);
+        for id in &body.source_ids {
+            delete = delete.bind(id);
+        }
+        let res = delete
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ApiError::internal_from("Failed to merge quizzes", e))?;
+        // A source deleted since it was read would otherwise be merged anyway
+        if res.rows_affected() as usize != body.source_ids.len() {
+            return Err(ApiError::Conflict(json!({
+                "error": "A source quiz was deleted while merging",
+                "code": "source_quiz_deleted"
+            })));
+        }
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to merge quizzes", e))?;
+
+    Ok(HttpResponse::Created().json(json!({
+        "id": new_id,
+        "message": "Quizzes merged successfully",
+        "owner_token": owner_token
+    })))
+}
+
+/// Update a quiz, replacing its title, description and full question set
+///
+/// The existing questions are deleted and re-inserted from the payload in one
+/// transaction, so readers never observe a partially updated quiz.
+///
+/// The payload must carry the `version` the client read; if the quiz has been
+/// updated since, nothing is written and 409 is returned with the current
+/// version.
+pub async fn update_quiz(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    question_counts: web::Data<QuestionCounts>,
+    quiz_id: web::Path<i32>,
+    mut quiz_data: web::Json<CreateQuizRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+    require_owner(pool, &req, &[id]).await?;
+    quiz_data.normalize();
+
+    validate_quiz_request(&quiz_data).map_err(ApiError::BadRequest)?;
+    let version = match quiz_data.version {
+        Some(v) => v,
+        None => {
+            return Err(ApiError::bad_request(
+                "version_required",
+                "version is required",
+            ))
+        }
+    };
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to update quiz", e))?;
+
+    let res = sqlx::query(
+        "UPDATE quizzes SET title = ?, title_key = ?, description = ?, jlpt_level = ?, updated_at = datetime('now'), version = version + 1 WHERE id = ? AND deleted_at IS NULL AND version = ?",
+    )
+    .bind(&quiz_data.title)
+    .bind(tools::title_key(&quiz_data.title))
+    .bind(&quiz_data.description)
+    .bind(&quiz_data.jlpt_level)
+    .bind(id)
+    .bind(version)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| ApiError::internal_from("Failed to update quiz", e))?;
+
+    if res.rows_affected() == 0 {
+        // Either the quiz is gone or someone else updated it first
+        return match sqlx::query_scalar::<_, i64>(
+            "SELECT version FROM quizzes WHERE id = ? AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await
+        {
+            Ok(Some(current)) => Err(ApiError::Conflict(json!({
+                "error": "Quiz was modified since it was read",
+                "code": "version_conflict",
+                "current_version": current
+            }))),
+            Ok(None) => Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+            Err(e) => Err(ApiError::internal_from("Failed to update quiz", e)),
+        };
+    }
+
+    queries::remove_quiz_questions(&mut tx, id)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to update quiz", e))?;
+
+    insert_questions(&mut tx, id, &quiz_data.questions)
+        .await
+        .map_err(ApiError::internal)?;
+
+    set_quiz_tags(&mut tx, id, &quiz_data.tags)
+        .await
+        .map_err(ApiError::internal)?;
+
+    tx.commit()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to update quiz", e))?;
+    question_counts.invalidate(id);
+
+    match fetch_quiz(pool, id).await {
+        Ok(Some(quiz)) => Ok(HttpResponse::Ok().json(quiz)),
+        Ok(None) => Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+        Err(e) => Err(ApiError::internal_from("Failed to fetch questions", e)),
+    }
+}
+
+/// Update only the quiz-level fields given in the body (title, description,
+/// jlpt_level, status), leaving the questions alone. Bumps `updated_at` and
+/// `version` and returns the updated quiz.
+///
+/// `version` is optional here; when given, a quiz that has been updated
+/// since gets a 409 with the current version, as with `PUT`.
+pub async fn patch_quiz(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    quiz_id: web::Path<i32>,
+    body: web::Json<UpdateQuizRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+    require_owner(pool, &req, &[id]).await?;
+    let body = body.into_inner();
+
+    if body.is_empty() {
+        return Err(ApiError::bad_request(
+            "nothing_to_update",
+            "Nothing to update; give title, description, jlpt_level or status",
+        ));
+    }
+
+    let current = fetch_quiz(pool, id)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to update quiz", e))?
+        .ok_or_else(|| ApiError::not_found("quiz_not_found", "Quiz not found"))?;
+
+    let blank_to_none = |value: String| {
+        let value = value.trim().to_string();
+        (!value.is_empty()).then_some(value)
+    };
+    let title = body
+        .title
+        .map(|t| t.trim().to_string())
+        .unwrap_or(current.title);
+    let description = match body.description {
+        Some(d) => blank_to_none(d),
+        None => current.description,
+    };
+    let jlpt_level = match body.jlpt_level {
+        Some(level) => blank_to_none(level),
+        None => current.jlpt_level,
+    };
+    let status = body.status.unwrap_or(current.status);
+
+    validate_quiz_metadata(&title, jlpt_level.as_deref()).map_err(ApiError::BadRequest)?;
+
+    // Without a client version, the version just read still guards against
+    // overwriting a concurrent update with stale values
+    let version = body.version.unwrap_or(current.version);
+    let res = sqlx::query(
+        "UPDATE quizzes SET title = ?, title_key = ?, description = ?, jlpt_level = ?, status = ?, updated_at = datetime('now'), version = version + 1 WHERE id = ? AND deleted_at IS NULL AND version = ?",
+    )
+    .bind(&title)
+    .bind(tools::title_key(&title))
+    .bind(&description)
+    .bind(&jlpt_level)
+    .bind(status.as_str())
+    .bind(id)
+    .bind(version)
+    .execute(pool)
+    .await
+    .map_err(|e| ApiError::internal_from("Failed to update quiz", e))?;
+
+    if res.rows_affected() == 0 {
+        return match sqlx::query_scalar::<_, i64>(
+            "SELECT version FROM quizzes WHERE id = ? AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        {
+            Ok(Some(current)) => Err(ApiError::Conflict(json!({
+                "error": "Quiz was modified since it was read",
+                "code": "version_conflict",
+                "current_version": current
+            }))),
+            Ok(None) => Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+            Err(e) => Err(ApiError::internal_from("Failed to update quiz", e)),
+        };
+    }
+
+    match fetch_quiz(pool, id).await {
+        Ok(Some(quiz)) => Ok(HttpResponse::Ok().json(quiz)),
+        Ok(None) => Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+        Err(e) => Err(ApiError::internal_from("Failed to fetch questions", e)),
+    }
+}
+
+/// Soft-delete a quiz
+///
+/// Only `deleted_at` is set; the quiz and its questions stay in the database
+/// and can be brought back with `restore_quiz`. Soft-deleted quizzes are hidden
+/// from every other endpoint.
+pub async fn delete_quiz(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    quiz_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+    require_owner(pool, &req, &[id]).await?;
+
+    let res = sqlx::query(
+        "UPDATE quizzes SET deleted_at = datetime('now') WHERE id = ? AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| ApiError::internal_from("Failed to delete quiz", e))?;
+
+    if res.rows_affected() == 0 {
+        return Err(ApiError::not_found("quiz_not_found", "Quiz not found"));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Most quiz ids accepted by one bulk delete
+const MAX_BULK_DELETE_IDS: usize = 100;
+
+/// Soft-delete every quiz in `ids` (at most `MAX_BULK_DELETE_IDS`) in one
+/// transaction, as `delete_quiz` does for one
+///
+/// Responds with `{ deleted, not_found }`: how many quizzes were deleted and
+/// the listed ids that don't exist or were already deleted. `X-Owner-Token`
+/// must hold the tokens of all listed quizzes that have one, comma-separated.
+pub async fn bulk_delete_quizzes(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    body: web::Json<BulkDeleteRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let mut ids = body.into_inner().ids;
+    ids.sort_unstable();
+    ids.dedup();
+
+    if ids.is_empty() {
+        return Err(ApiError::bad_request(
+            "no_ids_given",
+            "ids must list at least one quiz",
+        ));
+    }
+    if ids.len() > MAX_BULK_DELETE_IDS {
+        return Err(ApiError::bad_request(
+            "too_many_ids",
+            format!(
+                "ids may list at most {} quizzes, got {}",
+                MAX_BULK_DELETE_IDS,
+                ids.len()
+            ),
+        ));
+    }
+    require_owner(pool, &req, &ids).await?;
+
+    let placeholders = vec!["?"; ids.len()].join(", ");
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to delete quizzes", e))?;
+
+    let select_sql = format!(
+        "SELECT id FROM quizzes WHERE deleted_at IS NULL AND id IN ({})",
+        placeholders
+    );
+    let mut select_q = sqlx::query_scalar::<_, i64>(&select_sql);
+    for id in &ids {
+        select_q = select_q.bind(id);
+    }
+    let found: HashSet<i64> = select_q
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to delete quizzes", e))?
+        .into_iter()
+        .collect();
+
+    let update_sql = format!(
+        "UPDATE quizzes SET deleted_at = datetime('now') WHERE deleted_at IS NULL AND id IN ({})",
+        placeholders
+    );
+    let mut update_q = sqlx::query(&update_sql);
+    for id in &ids {
+        update_q = update_q.bind(id);
+    }
+    let deleted = update_q
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to delete quizzes", e))?
+        .rows_affected();
+
+    tx.commit()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to delete quizzes", e))?;
+
+    let not_found: Vec<i64> = ids.into_iter().filter(|id| !found.contains(id)).collect();
+    Ok(HttpResponse::Ok().json(json!({ "deleted": deleted, "not_found": not_found })))
+}
+
+/// Publish a draft quiz so it shows up in listings, returning the quiz.
+/// Publishing an already published quiz is a no-op.
+pub async fn publish_quiz(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    quiz_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+    require_owner(pool, &req, &[id]).await?;
+
+    let res = sqlx::query(
+        "UPDATE quizzes SET updated_at = CASE WHEN status = ? THEN updated_at ELSE datetime('now') END, status = ? WHERE id = ? AND deleted_at IS NULL",
+    )
+    .bind(QuizStatus::Published.as_str())
+    .bind(QuizStatus::Published.as_str())
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| ApiError::internal_from("Failed to publish quiz", e))?;
+
+    if res.rows_affected() == 0 {
+        return Err(ApiError::not_found("quiz_not_found", "Quiz not found"));
+    }
+
+    match fetch_quiz(pool, id).await {
+        Ok(Some(quiz)) => Ok(HttpResponse::Ok().json(quiz)),
+        Ok(None) => Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+        Err(e) => Err(ApiError::internal_from("Failed to fetch questions", e)),
+    }
+}
+
+/// Star a quiz, returning the updated quiz. Starring twice is a no-op.
+pub async fn favorite_quiz(
+    pool: web::Data<SqlitePool>,
+    quiz_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    set_favorite(pool.get_ref(), quiz_id.into_inner() as i64, true).await
+}
+
+/// Remove the star from a quiz, returning the updated quiz
+pub async fn unfavorite_quiz(
+    pool: web::Data<SqlitePool>,
+    quiz_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    set_favorite(pool.get_ref(), quiz_id.into_inner() as i64, false).await
+}
+
+/// Set the `favorite` flag of quiz `id`. Leaves `updated_at` and `version`
+/// alone, since starring doesn't change the quiz itself.
+async fn set_favorite(
+    pool: &SqlitePool,
+    id: i64,
+    favorite: bool,
+) -> Result<HttpResponse, ApiError> {
+    let res = sqlx::query("UPDATE quizzes SET favorite = ? WHERE id = ? AND deleted_at IS NULL")
+        .bind(favorite)
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to update favorite", e))?;
+
+    if res.rows_affected() == 0 {
+        return Err(ApiError::not_found("quiz_not_found", "Quiz not found"));
+    }
+
+    match fetch_quiz(pool, id).await {
+        Ok(Some(quiz)) => Ok(HttpResponse::Ok().json(quiz)),
+        Ok(None) => Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+        Err(e) => Err(ApiError::internal_from("Failed to fetch questions", e)),
+    }
+}
+
+/// Undo a soft delete, returning the restored quiz
+pub async fn restore_quiz(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    quiz_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+    require_owner(pool, &req, &[id]).await?;
+
+    let res =
+        sqlx::query("UPDATE quizzes SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL")
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e| ApiError::internal_from("Failed to restore quiz", e))?;
+
+    if res.rows_affected() == 0 {
+        return Err(ApiError::not_found(
+            "deleted_quiz_not_found",
+            "Deleted quiz not found",
+        ));
+    }
+
+    match fetch_quiz(pool, id).await {
+        Ok(Some(quiz)) => Ok(HttpResponse::Ok().json(quiz)),
+        Ok(None) => Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+        Err(e) => Err(ApiError::internal_from("Failed to fetch questions", e)),
+    }
+}
+
+/// Copy a quiz and all of its questions into a new quiz titled "<title> (copy)"
+///
+/// The copy has an owner token of its own, returned as `owner_token`.
+pub async fn duplicate_quiz(
+    pool: web::Data<SqlitePool>,
+    quiz_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+
+    let source = fetch_quiz(pool, id)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to fetch questions", e))?
+        .ok_or_else(|| ApiError::not_found("quiz_not_found", "Quiz not found"))?;
+
+    let copy = CreateQuizRequest {
+        title: format!("{} (copy)", source.title),
+        description: source.description,
+        jlpt_level: source.jlpt_level,
+        tags: source.tags,
+        version: None,
+        questions: source
+            .questions
+            .into_iter()
+            .map(QuestionInput::from)
+            .collect(),
+    };
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to duplicate quiz", e))?;
+
+    let (new_id, owner_token) = insert_quiz(&mut tx, &copy)
+        .await
+        .map_err(ApiError::internal)?;
+
+    tx.commit()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to duplicate quiz", e))?;
+
+    Ok(HttpResponse::Created().json(json!({
+        "id": new_id,
+        "message": "Quiz duplicated successfully",
+        "owner_token": owner_token
+    })))
+}
+
+/// Variants created by `create_variants` when `?count` is not given
+const DEFAULT_VARIANT_COUNT: i64 = 2;
+/// Most variants per request; they are lettered A to Z
+const MAX_VARIANTS: i64 = 26;
+/// Reshuffles tried before accepting a question order an earlier variant has
+const VARIANT_SHUFFLE_ATTEMPTS: usize = 10;
+
+/// Create `?count` exam variants of a quiz: copies titled
+/// "<title> - Variant A", "<title> - Variant B", ..., each with its questions
+/// in a different random order and every question's options shuffled
+/// (correct answers follow their options). Responds 201 with the new `ids`
+/// and their `owner_tokens`, in the same order.
+///
+/// A quiz with few questions has few distinct orders, so variants may
+/// repeat an order once those run out.
+pub async fn create_variants(
+    pool: web::Data<SqlitePool>,
+    quiz_id: web::Path<i32>,
+    query: web::Query<VariantsQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+
+    let count = query.count.unwrap_or(DEFAULT_VARIANT_COUNT);
+    if !(1..=MAX_VARIANTS).contains(&count) {
+        return Err(ApiError::bad_request(
+            "invalid_count",
+            format!("count must be between 1 and {}", MAX_VARIANTS),
+        ));
+    }
+
+    let source = fetch_quiz(pool, id)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to fetch questions", e))?
+        .ok_or_else(|| ApiError::not_found("quiz_not_found", "Quiz not found"))?;
+
+    let mut rng = rand::thread_rng();
+    let mut used_orders: Vec<Vec<Option<i32>>> = Vec::new();
+    let mut variants = Vec::new();
+    for letter in ('A'..='Z').take(count as usize) {
+        let mut questions = source.questions.clone();
+        for _ in 0..VARIANT_SHUFFLE_ATTEMPTS {
+            questions.shuffle(&mut rng);
+            let order: Vec<Option<i32>> = questions.iter().map(|q| q.id).collect();
+            if !used_orders.contains(&order) {
+                used_orders.push(order);
+                break;
+            }
+        }
+        for question in &mut questions {
+            question.shuffle_options(&mut rng);
+        }
+
+        variants.push(CreateQuizRequest {
+            title: format!("{} - Variant {}", source.title, letter),
+            description: source.description.clone(),
+            jlpt_level: source.jlpt_level.clone(),
+            tags: source.tags.clone(),
+            version: None,
+            questions: questions.into_iter().map(QuestionInput::from).collect(),
+        });
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to create variants", e))?;
+
+    let mut ids = Vec::with_capacity(variants.len());
+    let mut owner_tokens = Vec::with_capacity(variants.len());
+    for variant in &variants {
+        match insert_quiz(&mut tx, variant).await {
+            Ok((id, owner_token)) => {
+                ids.push(id);
+                owner_tokens.push(owner_token);
+            }
+            Err(msg) => return Err(ApiError::internal(msg)),
+        }
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to create variants", e))?;
+
+    Ok(HttpResponse::Created().json(json!({ "ids": ids, "owner_tokens": owner_tokens })))
+}
+
+/// Reorder a quiz's questions
+///
+/// `order` must list each of the quiz's question ids exactly once; positions
+/// are rewritten in one transaction.
+pub async fn reorder_questions(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    quiz_id: web::Path<i32>,
+    body: web::Json<ReorderQuestionsRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+    require_owner(pool, &req, &[id]).await?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to reorder questions", e))?;
+
+    match sqlx::query("SELECT id FROM quizzes WHERE id = ? AND deleted_at IS NULL")
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await
+    {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+        Err(e) => return Err(ApiError::internal_from("Failed to reorder questions", e)),
+    }
+
+    let mut existing: Vec<i64> =
+        sqlx::query_scalar("SELECT question_id FROM quiz_questions WHERE quiz_id = ?")
+            .bind(id)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| ApiError::internal_from("Failed to reorder questions", e))?;
+    existing.sort_unstable();
+    let mut requested = body.order.clone();
+    requested.sort_unstable();
+    if requested != existing {
+        return Err(ApiError::bad_request(
+            "invalid_order",
+            "order must list every question id of the quiz exactly once",
+        ));
+    }
+
+    for (position, question_id) in body.order.iter().enumerate() {
+        sqlx::query("UPDATE quiz_questions SET position = ? WHERE question_id = ? AND quiz_id = ?")
+            .bind(position as i64)
+            .bind(question_id)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ApiError::internal_from("Failed to reorder questions", e))?;
+    }
+
+    let failed = |e: sqlx::Error| ApiError::internal_from("Failed to reorder questions", e);
+    touch_quiz(&mut *tx, id).await.map_err(failed)?;
+    tx.commit().await.map_err(failed)?;
+
+    match fetch_quiz(pool, id).await {
+        Ok(Some(quiz)) => Ok(HttpResponse::Ok().json(quiz)),
+        Ok(None) => Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+        Err(e) => Err(ApiError::internal_from("Failed to fetch questions", e)),
+    }
+}
+
+/// Update some fields of a single question
+///
+/// The question must belong to `quiz_id`. The patched question is validated
+/// as a whole, so a new `correct_answer` is checked against the new or the
+/// stored options.
+pub async fn update_question(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    path: web::Path<(i32, i32)>,
+    patch: web::Json<UpdateQuestionRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let (quiz_id, question_id) = path.into_inner();
+    require_owner(pool, &req, &[quiz_id as i64]).await?;
+
+    let existing = fetch_question(pool, quiz_id as i64, question_id as i64)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to fetch question", e))?
+        .ok_or_else(|| ApiError::not_found("question_not_found", "Question not found"))?;
+
+    let mut question = QuestionInput::from(existing);
+    question.apply(patch.into_inner());
+    question.normalize();
+
+    question
+        .validate()
+        .map_err(|problem| ApiError::bad_request(problem.code, problem.message))?;
+
+    // The question and the quiz's updated_at change together or not at all
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to update question", e))?;
+    let updated = queries::update_question(&mut tx, quiz_id as i64, question_id as i64, &question)
+        .await
+        .map_err(ApiError::internal)?;
+    if !updated {
+        // removed from the quiz since it was read above
+        return Err(ApiError::not_found(
+            "question_not_found",
+            "Question not found",
+        ));
+    }
+    touch_quiz(&mut *tx, quiz_id as i64)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to update question", e))?;
+    tx.commit()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to update question", e))?;
+
+    match fetch_question(pool, quiz_id as i64, question_id as i64).await {
+        Ok(Some(q)) => Ok(HttpResponse::Ok().json(q)),
+        Ok(None) => Err(ApiError::not_found(
+            "question_not_found",
+            "Question not found",
+        )),
+        Err(e) => Err(ApiError::internal_from("Failed to fetch question", e)),
+    }
+}
+
+/// Get one question of a quiz
+///
+/// Returns 404 unless the question exists and belongs to `quiz_id`.
+pub async fn get_question(
+    pool: web::Data<SqlitePool>,
+    path: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let (quiz_id, question_id) = path.into_inner();
+
+    match fetch_question(pool, quiz_id as i64, question_id as i64).await {
+        Ok(Some(q)) => Ok(HttpResponse::Ok().json(q)),
+        Ok(None) => Err(ApiError::not_found(
+            "question_not_found",
+            "Question not found",
+        )),
+        Err(e) => Err(ApiError::internal_from("Failed to fetch question", e)),
+    }
+}
+
+/// Append one question (a JSON object) or several (an array) to a quiz
+///
+/// Each question is validated as on create and the quiz may not grow past
+/// `MAX_QUESTIONS`. Responds 201 with the created question(s), in the same
+/// shape as the request.
+pub async fn add_questions(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    question_counts: web::Data<QuestionCounts>,
+    quiz_id: web::Path<i32>,
+    body: web::Json<AddQuestionsRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+    require_owner(pool, &req, &[id]).await?;
+
+    let (single, questions) = checked_questions(body.into_inner())?;
+    append_questions(pool, &question_counts, id, &questions, single).await
+}
+
+/// Normalize and validate the question(s) of an add-questions body, and
+/// whether it was a single object rather than an array
+fn checked_questions(body: AddQuestionsRequest) -> Result<(bool, Vec<QuestionInput>), ApiError> {
+    let (single, mut questions) = match body {
+        AddQuestionsRequest::One(q) => (true, vec![*q]),
+        AddQuestionsRequest::Many(qs) => (false, qs),
+    };
+    if questions.is_empty() {
+        return Err(ApiError::bad_request(
+            "no_questions_given",
+            "No questions given",
+        ));
+    }
+    for (index, question) in questions.iter_mut().enumerate() {
+        question.normalize();
+        if let Err(problem) = question.validate() {
+            return Err(ApiError::BadRequest(json!({
+                "error": format!("Invalid question {}: {}", index, problem.message),
+                "code": problem.code,
+                "question_index": index
+            })));
+        }
+    }
+    Ok((single, questions))
+}
+
+/// Add one question (a JSON object) or several (an array) to the question
+/// bank, attached to no quiz; `attach_questions` then adds them to quizzes.
+///
+/// Each question is validated as on create. Responds 201 with the created
+/// question(s), in the same shape as the request.
+pub async fn create_bank_questions(
+    pool: web::Data<SqlitePool>,
+    body: web::Json<AddQuestionsRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let (single, questions) = checked_questions(body.into_inner())?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to create questions", e))?;
+    let ids = queries::insert_bank_questions(&mut tx, &questions)
+        .await
+        .map_err(ApiError::internal)?;
+    tx.commit()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to create questions", e))?;
+
+    let mut created = queries::fetch_questions_by_id(pool, &ids)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to fetch questions", e))?;
+    if single {
+        match created.pop() {
+            Some(q) => Ok(HttpResponse::Created().json(q)),
+            None => Err(ApiError::internal("Failed to fetch questions")),
+        }
+    } else {
+        Ok(HttpResponse::Created().json(created))
+    }
+}
+
+/// The distinct ids of a `question_ids` body, in first-seen order
+fn requested_question_ids(body: &QuestionIdsRequest) -> Result<Vec<i64>, ApiError> {
+    let mut ids: Vec<i64> = Vec::with_capacity(body.question_ids.len());
+    for id in &body.question_ids {
+        if !ids.contains(id) {
+            ids.push(*id);
+        }
+    }
+    if ids.is_empty() {
+        return Err(ApiError::bad_request(
+            "no_questions_given",
+            "question_ids must list at least one question",
+        ));
+    }
+    Ok(ids)
+}
+
+/// The question ids `sql` selects with `binds` bound in order
+async fn matching_question_ids(
+    conn: &mut SqliteConnection,
+    sql: &str,
+    binds: &[i64],
+) -> Result<HashSet<i64>, sqlx::Error> {
+    let mut q = sqlx::query_scalar::<_, i64>(sql);
+    for bind in binds {
+        q = q.bind(bind);
+    }
+    Ok(q.fetch_all(&mut *conn).await?.into_iter().collect())
+}
+
+/// Attach bank questions to the end of a quiz, in the order given
+///
+/// Every id must be a bank question (see `create_bank_questions`) not yet
+/// in the quiz, and the quiz may not grow past `MAX_QUESTIONS`. Responds
+/// with the updated quiz.
+pub async fn attach_questions(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    question_counts: web::Data<QuestionCounts>,
+    quiz_id: web::Path<i32>,
+    body: web::Json<QuestionIdsRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+    require_owner(pool, &req, &[id]).await?;
+    let ids = requested_question_ids(&body)?;
+    let placeholders = vec!["?"; ids.len()].join(", ");
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to attach questions", e))?;
+
+    let existing: Option<i64> = sqlx::query_scalar(
+        "SELECT (SELECT COUNT(*) FROM quiz_questions WHERE quiz_id = quizzes.id) FROM quizzes WHERE id = ? AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| ApiError::internal_from("Failed to attach questions", e))?;
+    let existing = match existing {
+        Some(count) => count as usize,
+        None => return Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+    };
+    if existing + ids.len() > MAX_QUESTIONS {
+        return Err(ApiError::bad_request(
+            "too_many_questions",
+            format!(
+                "A quiz may have at most {} questions; it has {} and {} were given",
+                MAX_QUESTIONS,
+                existing,
+                ids.len()
+            ),
+        ));
+    }
+
+    let bank_sql = format!(
+        "SELECT id FROM questions WHERE {} AND id IN ({})",
+        queries::BANK_QUESTION,
+        placeholders
+    );
+    let bank = matching_question_ids(&mut tx, &bank_sql, &ids)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to attach questions", e))?;
+    let not_in_bank: Vec<i64> = ids.iter().copied().filter(|q| !bank.contains(q)).collect();
+    if !not_in_bank.is_empty() {
+        return Err(ApiError::BadRequest(json!({
+            "error": "Only bank questions can be attached",
+            "code": "not_bank_questions",
+            "question_ids": not_in_bank
+        })));
+    }
+
+    let attached_sql = format!(
+        "SELECT question_id FROM quiz_questions WHERE quiz_id = ? AND question_id IN ({})",
+        placeholders
+    );
+    let attached = matching_question_ids(&mut tx, &attached_sql, &[&[id][..], &ids].concat())
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to attach questions", e))?;
+    if !attached.is_empty() {
+        let mut attached: Vec<i64> = attached.into_iter().collect();
+        attached.sort_unstable();
+        return Err(ApiError::Conflict(json!({
+            "error": "Some questions are already in the quiz",
+            "code": "already_attached",
+            "question_ids": attached
+        })));
+    }
+
+    queries::attach_questions(&mut tx, id, &ids)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to attach questions", e))?;
+    let failed = |e: sqlx::Error| ApiError::internal_from("Failed to attach questions", e);
+    touch_quiz(&mut *tx, id).await.map_err(failed)?;
+    tx.commit().await.map_err(failed)?;
+    question_counts.invalidate(id);
+
+    match fetch_quiz(pool, id).await {
+        Ok(Some(quiz)) => Ok(HttpResponse::Ok().json(quiz)),
+        Ok(None) => Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+        Err(e) => Err(ApiError::internal_from("Failed to fetch questions", e)),
+    }
+}
+
+/// Detach bank questions from a quiz; they stay in the bank and in any other
+/// quiz they are attached to
+///
+/// Every id must be a bank question attached to the quiz; questions written
+/// for the quiz are removed with `delete_question` instead. The remaining
+/// questions are renumbered. Responds with the updated quiz.
+pub async fn detach_questions(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    question_counts: web::Data<QuestionCounts>,
+    quiz_id: web::Path<i32>,
+    body: web::Json<QuestionIdsRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+    require_owner(pool, &req, &[id]).await?;
+    let ids = requested_question_ids(&body)?;
+    let placeholders = vec!["?"; ids.len()].join(", ");
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to detach questions", e))?;
+
+    match sqlx::query_scalar::<_, i64>("SELECT 1 FROM quizzes WHERE id = ? AND deleted_at IS NULL")
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await
+    {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+        Err(e) => return Err(ApiError::internal_from("Failed to detach questions", e)),
+    }
+
+    let attached_sql = format!(
+        "SELECT qq.question_id FROM quiz_questions qq JOIN questions ON questions.id = qq.question_id WHERE qq.quiz_id = ? AND {} AND qq.question_id IN ({})",
+        queries::BANK_QUESTION,
+        placeholders
+    );
+    let attached = matching_question_ids(&mut tx, &attached_sql, &[&[id][..], &ids].concat())
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to detach questions", e))?;
+    let not_attached: Vec<i64> = ids
+        .iter()
+        .copied()
+        .filter(|q| !attached.contains(q))
+        .collect();
+    if !not_attached.is_empty() {
+        return Err(ApiError::BadRequest(json!({
+            "error": "Only bank questions attached to the quiz can be detached",
+            "code": "not_attached",
+            "question_ids": not_attached
+        })));
+    }
+
+    let detach_sql = format!(
+        "DELETE FROM quiz_questions WHERE quiz_id = ? AND question_id IN ({})",
+        placeholders
+    );
+    let mut detach = sqlx::query(&detach_sql).bind(id);
+    for question_id in &ids {
+        detach = detach.bind(question_id);
+    }
+    let failed = |e: sqlx::Error| ApiError::internal_from("Failed to detach questions", e);
+    detach.execute(&mut *tx).await.map_err(failed)?;
+    queries::renumber_quiz_questions(&mut *tx, id)
+        .await
+        .map_err(failed)?;
+    touch_quiz(&mut *tx, id).await.map_err(failed)?;
+    tx.commit().await.map_err(failed)?;
+    question_counts.invalidate(id);
+
+    match fetch_quiz(pool, id).await {
+        Ok(Some(quiz)) => Ok(HttpResponse::Ok().json(quiz)),
+        Ok(None) => Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+        Err(e) => Err(ApiError::internal_from("Failed to fetch questions", e)),
+    }
+}
+
+/// Insert validated `questions` after the existing questions of quiz `id`,
+/// keeping it within `MAX_QUESTIONS`. Responds 201 with the created
+/// question (when `single`) or an array of them.
+async fn append_questions(
+    pool: &SqlitePool,
+    question_counts: &QuestionCounts,
+    id: i64,
+    questions: &[QuestionInput],
+    single: bool,
+) -> Result<HttpResponse, ApiError> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to create questions", e))?;
+
+    let existing: Option<i64> = sqlx::query_scalar(
+        "SELECT (SELECT COUNT(*) FROM quiz_questions WHERE quiz_id = quizzes.id) FROM quizzes WHERE id = ? AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| ApiError::internal_from("Failed to create questions", e))?;
+    let existing = match existing {
+        Some(count) => count as usize,
+        None => return Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+    };
+    if existing + questions.len() > MAX_QUESTIONS {
+        return Err(ApiError::bad_request(
+            "too_many_questions",
+            format!(
+                "A quiz may have at most {} questions; it has {} and {} were given",
+                MAX_QUESTIONS,
+                existing,
+                questions.len()
+            ),
+        ));
+    }
+
+    let ids = insert_questions(&mut tx, id, questions)
+        .await
+        .map_err(ApiError::internal)?;
+    let failed = |e: sqlx::Error| ApiError::internal_from("Failed to create questions", e);
+    touch_quiz(&mut *tx, id).await.map_err(failed)?;
+    tx.commit().await.map_err(failed)?;
+    question_counts.invalidate(id);
+
+    let mut created = Vec::with_capacity(ids.len());
+    for question_id in ids {
+        match fetch_question(pool, id, question_id).await {
+            Ok(Some(q)) => created.push(q),
+            Ok(None) => {}
+            Err(e) => return Err(ApiError::internal_from("Failed to fetch question", e)),
+        }
+    }
+
+    if single {
+        match created.pop() {
+            Some(q) => Ok(HttpResponse::Created().json(q)),
+            None => Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+        }
+    } else {
+        Ok(HttpResponse::Created().json(created))
+    }
+}
+
+/// Append questions to a quiz from a CSV body (see `import::questions_from_csv`)
+///
+/// Every row is checked before anything is stored; any problem gives a 400
+/// listing each bad row as `{ line, error }`. Responds 201 with the created
+/// questions.
+pub async fn import_questions_csv(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    question_counts: web::Data<QuestionCounts>,
+    quiz_id: web::Path<i32>,
+    body: String,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+    require_owner(pool, &req, &[id]).await?;
+
+    let questions = match import::questions_from_csv(&body) {
+        Ok(questions) => questions,
+        Err(errors) => {
+            return Err(ApiError::BadRequest(
+                json!({"error": "Invalid CSV", "code": "invalid_csv", "errors": errors}),
+            ))
+        }
+    };
+    if questions.is_empty() {
+        return Err(ApiError::bad_request(
+            "no_questions_given",
+            "No questions given",
+        ));
+    }
+
+    append_questions(pool, &question_counts, id, &questions, false).await
+}
+
+/// Delete every question of a quiz, keeping the quiz row (and its id,
+/// tags and attempts)
+///
+/// Responds with `{ "deleted": n }`, or 404 when the quiz doesn't exist.
+pub async fn clear_questions(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    question_counts: web::Data<QuestionCounts>,
+    quiz_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+    require_owner(pool, &req, &[id]).await?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to delete questions", e))?;
+
+    match sqlx::query_scalar::<_, i64>("SELECT 1 FROM quizzes WHERE id = ? AND deleted_at IS NULL")
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await
+    {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+        Err(e) => return Err(ApiError::internal_from("Failed to delete questions", e)),
+    }
+
+    let deleted = match queries::remove_quiz_questions(&mut tx, id).await {
+        Ok(removed) => removed,
+        Err(e) => return Err(ApiError::internal_from("Failed to delete questions", e)),
+    };
+
+    let failed = |e: sqlx::Error| ApiError::internal_from("Failed to delete questions", e);
+    touch_quiz(&mut *tx, id).await.map_err(failed)?;
+    tx.commit().await.map_err(failed)?;
+    question_counts.invalidate(id);
+
+    Ok(HttpResponse::Ok().json(json!({ "deleted": deleted })))
+}
+
+/// Delete one question from a quiz
+///
+/// Returns 404 unless the question exists and belongs to `quiz_id`. A bank
+/// question is only detached (see `detach_questions`). The remaining
+/// questions are renumbered so positions stay contiguous.
+pub async fn delete_question(
+    req: HttpRequest,
+    pool: web::Data<SqlitePool>,
+    question_counts: web::Data<QuestionCounts>,
+    path: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, ApiError> {
     let pool = pool.get_ref();
+    let (quiz_id, question_id) = path.into_inner();
+    require_owner(pool, &req, &[quiz_id as i64]).await?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to delete question", e))?;
+
+    let res = sqlx::query(
+        "DELETE FROM quiz_questions WHERE question_id = ? AND quiz_id = ? AND quiz_id IN (SELECT id FROM quizzes WHERE deleted_at IS NULL)",
+    )
+    .bind(question_id as i64)
+    .bind(quiz_id as i64)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| ApiError::internal_from("Failed to delete question", e))?;
 
-    let rows =
-        match sqlx::query("SELECT id, title, description FROM quizzes ORDER BY created_at DESC")
-            .fetch_all(pool)
-            .await
-        {
-            Ok(r) => r,
-            Err(_) => {
-                return HttpResponse::InternalServerError().json(json!({
-                    "error": "Failed to fetch quizzes"
-                }));
-            }
-        };
+    if res.rows_affected() == 0 {
+        return Err(ApiError::not_found(
+            "question_not_found",
+            "Question not found",
+        ));
+    }
 
-    let mut quizzes = Vec::new();
-    for row in rows {
-        let quiz_id: i64 = row.try_get("id").unwrap_or(0);
+    // only a question written for this quiz goes; a bank question stays in
+    // the bank and its other quizzes
+    sqlx::query("DELETE FROM questions WHERE id = ? AND quiz_id = ?")
+        .bind(question_id as i64)
+        .bind(quiz_id as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to delete question", e))?;
 
-        let questions_rows = match sqlx::query(
-            "SELECT id, prompt, options, correct_answer FROM questions WHERE quiz_id = ?",
-        )
-        .bind(quiz_id)
-        .fetch_all(pool)
+    let failed = |e: sqlx::Error| ApiError::internal_from("Failed to delete question", e);
+    queries::renumber_quiz_questions(&mut *tx, quiz_id as i64)
         .await
-        {
-            Ok(r) => r,
-            Err(_) => Vec::new(),
-        };
+        .map_err(failed)?;
+    touch_quiz(&mut *tx, quiz_id as i64).await.map_err(failed)?;
+    tx.commit().await.map_err(failed)?;
+    question_counts.invalidate(quiz_id as i64);
 
-        let questions: Vec<Question> = questions_rows
-            .into_iter()
-            .map(|r| {
-                let id: i64 = r.try_get("id").unwrap_or(0);
-                let text: String = r.try_get("prompt").unwrap_or_default();
-                let options_text: String =
-                    r.try_get("options").unwrap_or_else(|_| "[]".to_string());
-                let options: Vec<String> = serde_json::from_str(&options_text).unwrap_or_default();
-                let correct_answer: i64 = r.try_get("correct_answer").unwrap_or(0);
-
-                Question {
-                    id: Some(id as i32),
-                    text,
-                    options,
-                    correct_answer: correct_answer as i32,
-                }
-            })
-            .collect();
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Score a submitted attempt and record it in `attempts`
+///
+/// `answers[i]` is the option index chosen for the quiz's i-th question and
+/// the optional `timings[i]` the milliseconds spent on it.
+/// An option index outside the question's options is a 400 naming the
+/// `question_index` rather than a wrong answer.
+/// Responds with `{ score, total, results, explanations }` where `results[i]`
+/// tells whether the i-th answer was correct and `explanations[i]` is the
+/// i-th question's explanation (`null` if it has none). `scoring` is
+/// `count`, or with `?weighted=true` it is `weighted` and the response adds
+/// `weighted_score` out of `weighted_total`, where each question is worth
+/// its difficulty. The recorded attempt keeps the plain count either way.
+pub async fn submit_quiz(
+    pool: web::Data<SqlitePool>,
+    quiz_id: web::Path<i32>,
+    query: web::Query<SubmitQuizQuery>,
+    submission: web::Json<SubmitQuizRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
 
-        let title: String = row.try_get("title").unwrap_or_default();
-        let description: Option<String> = row.try_get("description").ok();
+    let quiz = fetch_quiz(pool, id)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to fetch questions", e))?
+        .ok_or_else(|| ApiError::not_found("quiz_not_found", "Quiz not found"))?;
 
-        quizzes.push(Quiz {
-            id: quiz_id as i32,
-            title,
-            description,
-            questions,
-        });
+    if submission.answers.len() != quiz.questions.len() {
+        return Err(ApiError::bad_request(
+            "answer_count_mismatch",
+            format!(
+                "Expected {} answers, got {}",
+                quiz.questions.len(),
+                submission.answers.len()
+            ),
+        ));
+    }
+    if let Some(timings) = &submission.timings {
+        if timings.len() != quiz.questions.len() {
+            return Err(ApiError::bad_request(
+                "timing_count_mismatch",
+                format!(
+                    "Expected {} timings, got {}",
+                    quiz.questions.len(),
+                    timings.len()
+                ),
+            ));
+        }
+    }
+    for (index, (question, answer)) in quiz.questions.iter().zip(&submission.answers).enumerate() {
+        if let Some(option) = question.invalid_option_index(answer) {
+            return Err(ApiError::BadRequest(json!({
+                "error": format!(
+                    "Invalid answer {}: question has {} options, got index {}",
+                    index,
+                    question.options.len(),
+                    option
+                ),
+                "code": "invalid_option_index",
+                "question_index": index
+            })));
+        }
+    }
+
+    let results: Vec<bool> = quiz
+        .questions
+        .iter()
+        .zip(&submission.answers)
+        .map(|(question, answer)| question.is_correct(answer))
+        .collect();
+    let score = results.iter().filter(|correct| **correct).count();
+    let explanations: Vec<Option<&str>> = quiz
+        .questions
+        .iter()
+        .map(|q| q.explanation.as_deref())
+        .collect();
+
+    let answers_json = serde_json::to_string(&submission.answers)
+        .map_err(|e| ApiError::internal_from("Failed to serialize answers", e))?;
+    let timings_json = match &submission.timings {
+        Some(timings) => Some(
+            serde_json::to_string(timings)
+                .map_err(|e| ApiError::internal_from("Failed to serialize timings", e))?,
+        ),
+        None => None,
+    };
+
+    sqlx::query(
+        "INSERT INTO attempts (quiz_id, score, total, answers, timings) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(id)
+    .bind(score as i64)
+    .bind(results.len() as i64)
+    .bind(&answers_json)
+    .bind(&timings_json)
+    .execute(pool)
+    .await
+    .map_err(|e| ApiError::internal_from("Failed to save attempt", e))?;
+
+    let mut body = json!({
+        "score": score,
+        "total": results.len(),
+        "scoring": "count",
+        "results": results,
+        "explanations": explanations
+    });
+    if query.weighted.unwrap_or(false) {
+        let weighted_total: i64 = quiz.questions.iter().map(|q| i64::from(q.difficulty)).sum();
+        let weighted_score: i64 = quiz
+            .questions
+            .iter()
+            .zip(&results)
+            .filter(|(_, correct)| **correct)
+            .map(|(q, _)| i64::from(q.difficulty))
+            .sum();
+        body["scoring"] = json!("weighted");
+        body["weighted_score"] = json!(weighted_score);
+        body["weighted_total"] = json!(weighted_total);
     }
 
-    HttpResponse::Ok().json(quizzes)
+    Ok(HttpResponse::Ok().json(body))
 }
 
-/// Get a single quiz
-pub async fn get_quiz(pool: web::Data<SqlitePool>, quiz_id: web::Path<i32>) -> impl Responder {
+/// List recorded attempts for a quiz, newest first
+pub async fn list_attempts(
+    pool: web::Data<SqlitePool>,
+    quiz_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
     let pool = pool.get_ref();
     let id = quiz_id.into_inner() as i64;
 
-    let quiz_row = match sqlx::query("SELECT id, title, description FROM quizzes WHERE id = ?")
+    match sqlx::query("SELECT id FROM quizzes WHERE id = ? AND deleted_at IS NULL")
         .bind(id)
-        .fetch_one(pool)
+        .fetch_optional(pool)
         .await
     {
-        Ok(r) => r,
-        Err(_) => return HttpResponse::NotFound().json(json!({"error": "Quiz not found"})),
-    };
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+        Err(e) => return Err(ApiError::internal_from("Failed to fetch attempts", e)),
+    }
 
-    let questions_rows = match sqlx::query(
-        "SELECT id, prompt, options, correct_answer FROM questions WHERE quiz_id = ?",
+    let rows = sqlx::query(
+        "SELECT id, quiz_id, score, total, answers, timings, created_at FROM attempts WHERE quiz_id = ? ORDER BY created_at DESC, id DESC",
     )
     .bind(id)
     .fetch_all(pool)
     .await
-    {
-        Ok(r) => r,
-        Err(_) => {
-            return HttpResponse::InternalServerError()
-                .json(json!({"error": "Failed to fetch questions"}))
-        }
-    };
+    .map_err(|e| ApiError::internal_from("Failed to fetch attempts", e))?;
 
-    let questions: Vec<Question> = questions_rows
-        .into_iter()
+    let attempts: Vec<Attempt> = rows
+        .iter()
         .map(|r| {
-            let id: i64 = r.try_get("id").unwrap_or(0);
-            let text: String = r.try_get("prompt").unwrap_or_default();
-            let options_text: String = r.try_get("options").unwrap_or_else(|_| "[]".to_string());
-            let options: Vec<String> = serde_json::from_str(&options_text).unwrap_or_default();
-            let correct_answer: i64 = r.try_get("correct_answer").unwrap_or(0);
-
-            Question {
-                id: Some(id as i32),
-                text,
-                options,
-                correct_answer: correct_answer as i32,
+            let answers_text: String = r.try_get("answers").unwrap_or_else(|_| "[]".to_string());
+            let timings_text: Option<String> = r.try_get("timings").ok().flatten();
+            Attempt {
+                id: r.try_get("id").unwrap_or(0),
+                quiz_id: r.try_get("quiz_id").unwrap_or(0),
+                score: r.try_get("score").unwrap_or(0),
+                total: r.try_get("total").unwrap_or(0),
+                answers: serde_json::from_str(&answers_text).unwrap_or_default(),
+                timings: timings_text.and_then(|t| serde_json::from_str(&t).ok()),
+                created_at: rfc3339(r.try_get("created_at").ok().flatten()),
             }
         })
         .collect();
 
-    let quiz = Quiz {
-        id: quiz_row.try_get::<i64, _>("id").unwrap_or(0) as i32,
-        title: quiz_row.try_get::<String, _>("title").unwrap_or_default(),
-        description: quiz_row
-            .try_get::<Option<String>, _>("description")
-            .ok()
-            .flatten(),
-        questions,
-    };
+    Ok(HttpResponse::Ok().json(attempts))
+}
+
+/// How often each question of a quiz is answered correctly
+///
+/// Attempts store answers by position, so only attempts whose answer count
+/// matches the quiz's current question count are used; older attempts made
+/// before questions were added or removed can't be lined up reliably. Returns
+/// `{question_id, text, attempts, correct, percent_correct, average_time_ms}`
+/// per question, lowest `percent_correct` first; questions nobody has
+/// answered come last with `percent_correct: null`. `average_time_ms` only
+/// counts attempts that reported timings and is `null` when none did.
+pub async fn question_stats(
+    pool: web::Data<SqlitePool>,
+    quiz_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+
+    let quiz = fetch_quiz(pool, id)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to fetch questions", e))?
+        .ok_or_else(|| ApiError::not_found("quiz_not_found", "Quiz not found"))?;
 
-    HttpResponse::Ok().json(quiz)
+    let mut stats = question_stat_entries(pool, &quiz).await?;
+    // Unanswered (None) sorts after every percentage
+    stats.sort_by(|(a, _), (b, _)| match (a, b) {
+        (Some(a), Some(b)) => a.total_cmp(b),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+
+    Ok(HttpResponse::Ok().json(stats.into_iter().map(|(_, e)| e).collect::<Vec<_>>()))
 }
 
-/// Create a quiz (and its questions)
-pub async fn create_quiz(
+/// The `question_stats` entry of each question of `quiz`, in quiz order,
+/// paired with its `percent_correct` for sorting
+async fn question_stat_entries(
+    pool: &SqlitePool,
+    quiz: &Quiz,
+) -> Result<Vec<(Option<f64>, JsonValue)>, ApiError> {
+    let answer_rows: Vec<(String, Option<String>)> =
+        sqlx::query_as("SELECT answers, timings FROM attempts WHERE quiz_id = ?")
+            .bind(quiz.id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| ApiError::internal_from("Failed to fetch attempts", e))?;
+
+    let mut attempts = vec![0usize; quiz.questions.len()];
+    let mut correct = vec![0usize; quiz.questions.len()];
+    let mut timed = 0u64;
+    let mut total_ms = vec![0u64; quiz.questions.len()];
+    for (text, timings_text) in &answer_rows {
+        let answers: Vec<SubmittedAnswer> = match serde_json::from_str(text) {
+            Ok(a) => a,
+            Err(_) => continue,
+        };
+        if answers.len() != quiz.questions.len() {
+            continue;
+        }
+        for (i, (question, answer)) in quiz.questions.iter().zip(&answers).enumerate() {
+            attempts[i] += 1;
+            if question.is_correct(answer) {
+                correct[i] += 1;
+            }
+        }
+        let timings: Option<Vec<u32>> = timings_text
+            .as_deref()
+            .and_then(|t| serde_json::from_str(t).ok());
+        if let Some(timings) = timings.filter(|t| t.len() == quiz.questions.len()) {
+            timed += 1;
+            for (sum, ms) in total_ms.iter_mut().zip(timings) {
+                *sum += u64::from(ms);
+            }
+        }
+    }
+
+    Ok(quiz
+        .questions
+        .iter()
+        .enumerate()
+        .map(|(i, question)| {
+            let percent = (attempts[i] > 0)
+                .then(|| (correct[i] as f64 * 10_000.0 / attempts[i] as f64).round() / 100.0);
+            let entry = json!({
+                "question_id": question.id,
+                "text": question.text,
+                "attempts": attempts[i],
+                "correct": correct[i],
+                "percent_correct": percent,
+                "average_time_ms": (timed > 0)
+                    .then(|| (total_ms[i] as f64 / timed as f64).round() as u64),
+            });
+            (percent, entry)
+        })
+        .collect())
+}
+
+/// Everything about how a quiz is doing, for its analytics page
+///
+/// Responds with `{ attempts, average_percent, highest_percent,
+/// lowest_percent, questions }`: the number of recorded attempts, the mean,
+/// best and worst score as a percentage of the attempt's total, and the
+/// `question_stats` entry of each question in quiz order. A quiz nobody has
+/// taken yet gets zeros, and `percent_correct: null` per question.
+pub async fn quiz_stats(
     pool: web::Data<SqlitePool>,
-    quiz_data: web::Json<CreateQuizRequest>,
-) -> impl Responder {
+    quiz_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
     let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
 
-    let res = match sqlx::query("INSERT INTO quizzes (title, description) VALUES (?, ?)")
-        .bind(&quiz_data.title)
-        .bind(&quiz_data.description)
-        .execute(pool)
+    let quiz = fetch_quiz(pool, id)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to fetch questions", e))?
+        .ok_or_else(|| ApiError::not_found("quiz_not_found", "Quiz not found"))?;
+
+    // An attempt's total is 0 only for a quiz that had no questions; such
+    // attempts count but have no percentage
+    let (attempts, average, highest, lowest): (i64, f64, f64, f64) = sqlx::query_as(
+        "SELECT COUNT(*), \
+                COALESCE(AVG(score * 100.0 / NULLIF(total, 0)), 0.0), \
+                COALESCE(MAX(score * 100.0 / NULLIF(total, 0)), 0.0), \
+                COALESCE(MIN(score * 100.0 / NULLIF(total, 0)), 0.0) \
+         FROM attempts WHERE quiz_id = ?",
+    )
+    .bind(id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| ApiError::internal_from("Failed to fetch attempts", e))?;
+
+    let questions: Vec<JsonValue> = question_stat_entries(pool, &quiz)
+        .await?
+        .into_iter()
+        .map(|(_, entry)| entry)
+        .collect();
+    let round = |percent: f64| (percent * 100.0).round() / 100.0;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "quiz_id": id,
+        "attempts": attempts,
+        "average_percent": round(average),
+        "highest_percent": round(highest),
+        "lowest_percent": round(lowest),
+        "questions": questions
+    })))
+}
+
+/// Leaderboard size used when `?limit` is omitted
+const DEFAULT_LEADERBOARD_LIMIT: i64 = 10;
+/// Largest leaderboard `?limit` accepted
+const MAX_LEADERBOARD_LIMIT: i64 = 50;
+
+/// Top attempts for a quiz: highest score first, earlier attempts winning ties
+pub async fn leaderboard(
+    pool: web::Data<SqlitePool>,
+    quiz_id: web::Path<i32>,
+    query: web::Query<LeaderboardQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_LEADERBOARD_LIMIT)
+        .clamp(1, MAX_LEADERBOARD_LIMIT);
+
+    match sqlx::query("SELECT id FROM quizzes WHERE id = ? AND deleted_at IS NULL")
+        .bind(id)
+        .fetch_optional(pool)
         .await
     {
-        Ok(r) => r,
-        Err(_) => {
-            return HttpResponse::InternalServerError()
-                .json(json!({"error":"Failed to create quiz"}))
-        }
-    };
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+        Err(e) => return Err(ApiError::internal_from("Failed to fetch leaderboard", e)),
+    }
 
-    let quiz_id = res.last_insert_rowid() as i32;
+    let rows = sqlx::query(
+        "SELECT id, score, total, created_at FROM attempts WHERE quiz_id = ? ORDER BY score DESC, created_at ASC, id ASC LIMIT ?",
+    )
+    .bind(id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ApiError::internal_from("Failed to fetch leaderboard", e))?;
 
-    for question in &quiz_data.questions {
-        let options_json = match serde_json::to_string(&question.options) {
-            Ok(s) => s,
-            Err(_) => {
-                return HttpResponse::InternalServerError()
-                    .json(json!({"error":"Failed to serialize question options"}))
-            }
-        };
+    let entries: Vec<JsonValue> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            json!({
+                "rank": i + 1,
+                "attempt_id": r.try_get::<i64, _>("id").unwrap_or(0),
+                "score": r.try_get::<i64, _>("score").unwrap_or(0),
+                "total": r.try_get::<i64, _>("total").unwrap_or(0),
+                "created_at": rfc3339(r.try_get("created_at").ok().flatten()),
+            })
+        })
+        .collect();
 
-        if let Err(_) = sqlx::query(
-            "INSERT INTO questions (quiz_id, prompt, options, correct_answer) VALUES (?, ?, ?, ?)",
-        )
-        .bind(quiz_id as i64)
-        .bind(&question.text)
-        .bind(&options_json)
-        .bind(question.correct_answer as i64)
-        .execute(pool)
+    Ok(HttpResponse::Ok().json(entries))
+}
+
+/// Export a quiz as an attachment, one row per question
+///
+/// `?format=csv` (the default) gives a spreadsheet-friendly CSV,
+/// `?format=anki` a TSV that Anki imports as front/back notes.
+pub async fn export_quiz(
+    pool: web::Data<SqlitePool>,
+    quiz_id: web::Path<i32>,
+    query: web::Query<ExportQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+
+    let format = query.format.as_deref().unwrap_or("csv");
+    if !matches!(format, "csv" | "anki") {
+        return Err(ApiError::bad_request(
+            "unknown_export_format",
+            format!("Unknown export format '{}', expected csv or anki", format),
+        ));
+    }
+
+    let quiz = fetch_quiz(pool, id)
         .await
-        {
-            return HttpResponse::InternalServerError()
-                .json(json!({"error":"Failed to create questions"}));
-        }
+        .map_err(|e| ApiError::internal_from("Failed to fetch questions", e))?
+        .ok_or_else(|| ApiError::not_found("quiz_not_found", "Quiz not found"))?;
+
+    match format {
+        "anki" => Ok(HttpResponse::Ok()
+            .content_type("text/tab-separated-values; charset=utf-8")
+            .insert_header(export::attachment(&quiz, "tsv"))
+            .body(export::quiz_to_anki(&quiz))),
+        _ => Ok(HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .insert_header(export::attachment(&quiz, "csv"))
+            .body(export::quiz_to_csv(&quiz))),
     }
+}
 
-    HttpResponse::Created().json(json!({"id": quiz_id, "message": "Quiz created successfully"}))
+/// A quiz as a printable HTML handout with an answer key; see
+/// `export::quiz_to_html`
+pub async fn print_quiz(
+    pool: web::Data<SqlitePool>,
+    quiz_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let id = quiz_id.into_inner() as i64;
+
+    match fetch_quiz(pool, id).await {
+        Ok(Some(quiz)) => Ok(HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(export::quiz_to_html(&quiz))),
+        Ok(None) => Err(ApiError::not_found("quiz_not_found", "Quiz not found")),
+        Err(e) => Err(ApiError::internal_from("Failed to fetch questions", e)),
+    }
 }
 
 /// Create a test based on selection criteria and store it in `tests` table.
@@ -183,7 +2860,7 @@ pub async fn create_quiz(
 pub async fn create_test(
     pool: web::Data<SqlitePool>,
     payload: web::Json<JsonValue>,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     let pool = pool.get_ref();
     let body = payload.into_inner();
 
@@ -247,11 +2924,12 @@ pub async fn create_test(
     let order_clause = "ORDER BY RANDOM()";
 
     // Ensure tests table exists
-    if let Err(_) = sqlx::query(
+    sqlx::query(
         "CREATE TABLE IF NOT EXISTS tests (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT, questions TEXT, created_at TEXT DEFAULT (datetime('now')))"
-    ).execute(pool).await {
-        return HttpResponse::InternalServerError().json(json!({"error":"Failed to prepare tests storage"}));
-    }
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| ApiError::internal_from("Failed to prepare tests storage", e))?;
 
     let query_sql = format!("SELECT id, entry_id, q_type, prompt, options, correct_index, correct_answer FROM questions {} {} {}", where_sql, order_clause, limit_clause);
 
@@ -285,12 +2963,10 @@ pub async fn create_test(
         }
     }
 
-    let mut rows: Vec<SqliteRow> = match q.fetch_all(pool).await {
-        Ok(r) => r,
-        Err(_) => {
-            return HttpResponse::InternalServerError().json(json!({"error":"Database error"}))
-        }
-    };
+    let mut rows: Vec<SqliteRow> = q
+        .fetch_all(pool)
+        .await
+        .map_err(|e| ApiError::internal_from("Database error", e))?;
 
     // Simple fallback: if nothing selected, try global selection without filters
     if rows.is_empty() {
@@ -298,15 +2974,15 @@ pub async fn create_test(
         let q_any = sqlx::query(&query_any);
         match q_any.fetch_all(pool).await {
             Ok(r2) => rows = r2,
-            Err(_) => {
-                return HttpResponse::InternalServerError().json(json!({"error":"Database error"}))
-            }
+            Err(e) => return Err(ApiError::internal_from("Database error", e)),
         }
     }
 
     if rows.is_empty() {
-        return HttpResponse::BadRequest()
-            .json(json!({"error":"No questions matched the selection"}));
+        return Err(ApiError::bad_request(
+            "no_questions_matched",
+            "No questions matched the selection",
+        ));
     }
 
     let mut test_questions: Vec<JsonValue> = Vec::new();
@@ -327,73 +3003,566 @@ pub async fn create_test(
         }));
     }
 
-    let title = format!("Test - {} - {}", level_label, Utc::now().to_rfc3339());
-    let questions_json = match serde_json::to_string(&test_questions) {
-        Ok(s) => s,
-        Err(_) => {
-            return HttpResponse::InternalServerError()
-                .json(json!({"error":"Failed to serialize test"}))
-        }
-    };
+    let title = format!(
+        "Test - {} - {}",
+        level_label,
+        Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+    );
+    let questions_json = serde_json::to_string(&test_questions)
+        .map_err(|e| ApiError::internal_from("Failed to serialize test", e))?;
 
-    let res = match sqlx::query("INSERT INTO tests (title, questions) VALUES (?, ?)")
+    let res = sqlx::query("INSERT INTO tests (title, questions) VALUES (?, ?)")
         .bind(&title)
         .bind(&questions_json)
         .execute(pool)
         .await
-    {
-        Ok(r) => r,
-        Err(_) => {
-            return HttpResponse::InternalServerError().json(json!({"error":"Failed to save test"}))
-        }
-    };
+        .map_err(|e| ApiError::internal_from("Failed to save test", e))?;
 
     let test_id = res.last_insert_rowid();
     let redirect = format!("/test/{}", test_id);
 
-    HttpResponse::Created().json(json!({"id": test_id, "redirect": redirect}))
+    Ok(HttpResponse::Created().json(json!({"id": test_id, "redirect": redirect})))
 }
 
 /// Get a generated test by id
-pub async fn get_test(pool: web::Data<SqlitePool>, test_id: web::Path<i64>) -> impl Responder {
+pub async fn get_test(
+    pool: web::Data<SqlitePool>,
+    test_id: web::Path<i64>,
+) -> Result<HttpResponse, ApiError> {
     let pool = pool.get_ref();
     let id = test_id.into_inner();
 
-    let row = match sqlx::query("SELECT id, title, questions, created_at FROM tests WHERE id = ?")
+    let row = sqlx::query("SELECT id, title, questions, created_at FROM tests WHERE id = ?")
         .bind(id)
         .fetch_one(pool)
         .await
-    {
-        Ok(r) => r,
-        Err(_) => return HttpResponse::NotFound().json(json!({"error": "Test not found"})),
-    };
+        .map_err(|_| ApiError::not_found("test_not_found", "Test not found"))?;
 
     let questions_text: String = row
         .try_get("questions")
         .unwrap_or_else(|_| "[]".to_string());
-    let questions: JsonValue = match serde_json::from_str(&questions_text) {
-        Ok(v) => v,
-        Err(_) => {
-            return HttpResponse::InternalServerError()
-                .json(json!({"error":"Failed to parse test data"}))
-        }
-    };
+    let questions: JsonValue = serde_json::from_str(&questions_text)
+        .map_err(|e| ApiError::internal_from("Failed to parse test data", e))?;
 
     let title: String = row.try_get("title").unwrap_or_default();
 
-    HttpResponse::Ok().json(json!({
+    Ok(HttpResponse::Ok().json(json!({
         "id": id,
         "title": title,
-        "questions": questions
-    }))
+        "questions": questions,
+        "created_at": rfc3339(row.try_get("created_at").ok().flatten()),
+    })))
+}
+
+/// Most matching questions `search` returns
+const MAX_SEARCH_RESULTS: i64 = 200;
+
+/// Shortest query `search` looks up in the trigram index
+const MIN_INDEXED_SEARCH_CHARS: usize = 3;
+
+/// Escape `text` for HTML and wrap each occurrence of `needle` in `<mark>`.
+///
+/// Matching folds ASCII case only, like SQLite's `LIKE`, so byte offsets in
+/// the folded copy line up with the original.
+fn highlight(text: &str, needle: &str) -> String {
+    use export::html_escape as escape;
+
+    let haystack = text.to_ascii_lowercase();
+    let needle = needle.to_ascii_lowercase();
+    let mut out = String::with_capacity(text.len());
+    let mut pos = 0;
+    while let Some(found) = haystack[pos..].find(&needle) {
+        let start = pos + found;
+        let end = start + needle.len();
+        out.push_str(&escape(&text[pos..start]));
+        out.push_str("<mark>");
+        out.push_str(&escape(&text[start..end]));
+        out.push_str("</mark>");
+        pos = end;
+    }
+    out.push_str(&escape(&text[pos..]));
+    out
+}
+
+/// Find quizzes whose questions mention `?q` (in the text or the reading)
+///
+/// Returns an array of `{id, title, jlpt_level, questions}` with only the
+/// matching questions, each carrying a `highlighted_text` with the match
+/// wrapped in `<mark>`. No matches is an empty array, not a 404.
+pub async fn search(
+    pool: web::Data<SqlitePool>,
+    query: web::Query<SearchQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+
+    // Stored question text is NFKC-normalized, so the query must be too
+    let q = match query
+        .q
+        .as_deref()
+        .map(tools::normalize_answer)
+        .filter(|q| !q.is_empty())
+    {
+        Some(q) => q,
+        None => return Err(ApiError::bad_request("q_required", "q is required")),
+    };
+
+    // The trigram index (see migration 0009) is made of three-character
+    // substrings and can't narrow down a shorter query, such as a single
+    // kanji, so those fall back to a LIKE scan of the questions.
+    let (matching, binds) = if q.chars().count() >= MIN_INDEXED_SEARCH_CHARS {
+        // a quoted FTS5 string is matched as a literal substring
+        let phrase = format!("\"{}\"", q.replace('"', "\"\""));
+        (
+            "questions.id IN (SELECT rowid FROM questions_fts WHERE questions_fts MATCH ?)",
+            vec![phrase],
+        )
+    } else {
+        let pattern = escape_like(&q);
+        (
+            "(prompt LIKE '%' || ? || '%' ESCAPE '\\' OR reading LIKE '%' || ? || '%' ESCAPE '\\')",
+            vec![pattern.clone(), pattern],
+        )
+    };
+    let sql = format!(
+        "SELECT qq.quiz_id, {} FROM quiz_questions qq JOIN questions ON questions.id = qq.question_id WHERE qq.quiz_id IN (SELECT id FROM quizzes WHERE deleted_at IS NULL AND status = 'published') AND {} ORDER BY qq.quiz_id, qq.position, questions.id LIMIT ?",
+        QUESTION_COLUMNS, matching
+    );
+    let mut search_query = sqlx::query(&sql);
+    for bind in &binds {
+        search_query = search_query.bind(bind);
+    }
+    let rows = search_query
+        .bind(MAX_SEARCH_RESULTS)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| ApiError::internal_from("Search failed", e))?;
+
+    // Group matches by quiz, keeping quizzes in first-match order
+    let mut quiz_ids: Vec<i64> = Vec::new();
+    let mut matches: HashMap<i64, Vec<JsonValue>> = HashMap::new();
+    for r in &rows {
+        let quiz_id: i64 = r.try_get("quiz_id").unwrap_or(0);
+        let question = question_from_row(r);
+        let highlighted = highlight(&question.text, &q);
+        let mut value = serde_json::to_value(&question).unwrap_or_default();
+        if let JsonValue::Object(map) = &mut value {
+            map.insert("highlighted_text".to_string(), JsonValue::from(highlighted));
+        }
+        if !matches.contains_key(&quiz_id) {
+            quiz_ids.push(quiz_id);
+        }
+        matches.entry(quiz_id).or_default().push(value);
+    }
+
+    if quiz_ids.is_empty() {
+        return Ok(HttpResponse::Ok().json(json!([])));
+    }
+
+    let placeholders = vec!["?"; quiz_ids.len()].join(", ");
+    let sql = format!(
+        "SELECT id, title, jlpt_level FROM quizzes WHERE id IN ({})",
+        placeholders
+    );
+    let mut quizzes_query = sqlx::query(&sql);
+    for id in &quiz_ids {
+        quizzes_query = quizzes_query.bind(id);
+    }
+    let mut quizzes: HashMap<i64, SqliteRow> = match quizzes_query.fetch_all(pool).await {
+        Ok(rows) => rows
+            .into_iter()
+            .map(|r| (r.try_get::<i64, _>("id").unwrap_or(0), r))
+            .collect(),
+        Err(e) => return Err(ApiError::internal_from("Search failed", e)),
+    };
+
+    let results: Vec<JsonValue> = quiz_ids
+        .iter()
+        .filter_map(|id| {
+            let row = quizzes.remove(id)?;
+            Some(json!({
+                "id": id,
+                "title": row.try_get::<String, _>("title").unwrap_or_default(),
+                "jlpt_level": row.try_get::<Option<String>, _>("jlpt_level").ok().flatten(),
+                "questions": matches.remove(id).unwrap_or_default(),
+            }))
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(results))
+}
+
+/// Summary numbers for the admin dashboard
+///
+/// `quiz_count` excludes soft-deleted quizzes; questions and attempts are
+/// counted across the whole table.
+pub async fn stats(pool: web::Data<SqlitePool>) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+
+    match sqlx::query(
+        "SELECT (SELECT COUNT(*) FROM quizzes WHERE deleted_at IS NULL) AS quiz_count, (SELECT COUNT(*) FROM questions) AS question_count, (SELECT COUNT(*) FROM attempts) AS attempt_count",
+    )
+    .fetch_one(pool)
+    .await
+    {
+        Ok(row) => Ok(HttpResponse::Ok().json(json!({
+            "quiz_count": row.try_get::<i64, _>("quiz_count").unwrap_or(0),
+            "question_count": row.try_get::<i64, _>("question_count").unwrap_or(0),
+            "attempt_count": row.try_get::<i64, _>("attempt_count").unwrap_or(0),
+        }))),
+        Err(e) => Err(ApiError::internal_from("Failed to load stats", e)),
+    }
+}
+
+/// Longest `session` accepted by the review endpoints
+const MAX_SESSION_LEN: usize = 255;
+
+/// Format used for `review_state.next_review_at`, the same as `datetime('now')`
+const SQLITE_DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Check a review `session`, returning it trimmed. On failure returns the 400
+/// body.
+fn review_session(session: &str) -> Result<&str, JsonValue> {
+    let session = session.trim();
+    if session.is_empty() {
+        return Err(json!({"error": "session is required", "code": "session_required"}));
+    }
+    if session.len() > MAX_SESSION_LEN {
+        return Err(json!({
+            "error": format!("session must be at most {} bytes", MAX_SESSION_LEN),
+            "code": "session_too_long"
+        }));
+    }
+    Ok(session)
+}
+
+/// Record a review of a question and reschedule it (SM-2)
+///
+/// Responds with the question's new schedule for the session.
+pub async fn record_review(
+    pool: web::Data<SqlitePool>,
+    body: web::Json<ReviewRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let session = review_session(&body.session).map_err(ApiError::BadRequest)?;
+    if !(0..=srs::MAX_GRADE).contains(&body.grade) {
+        return Err(ApiError::bad_request(
+            "invalid_grade",
+            format!("grade must be between 0 and {}", srs::MAX_GRADE),
+        ));
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to record review", e))?;
+
+    let exists = sqlx::query_scalar::<_, i64>(
+        "SELECT 1 FROM quiz_questions WHERE question_id = ? AND quiz_id IN (SELECT id FROM quizzes WHERE deleted_at IS NULL) LIMIT 1",
+    )
+    .bind(body.question_id)
+    .fetch_optional(&mut *tx)
+    .await;
+    match exists {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return Err(ApiError::not_found(
+                "question_not_found",
+                "Question not found",
+            ))
+        }
+        Err(e) => return Err(ApiError::internal_from("Failed to record review", e)),
+    }
+
+    let previous = sqlx::query(
+        "SELECT repetitions, interval_days, ease_factor FROM review_state WHERE session = ? AND question_id = ?",
+    )
+    .bind(session)
+    .bind(body.question_id)
+    .fetch_optional(&mut *tx)
+    .await;
+    let previous = match previous {
+        Ok(Some(r)) => srs::Schedule {
+            repetitions: r.try_get("repetitions").unwrap_or(0),
+            interval_days: r.try_get("interval_days").unwrap_or(0),
+            ease_factor: r.try_get("ease_factor").unwrap_or(srs::INITIAL_EASE_FACTOR),
+        },
+        Ok(None) => srs::Schedule::default(),
+        Err(e) => return Err(ApiError::internal_from("Failed to record review", e)),
+    };
+
+    let schedule = previous.review(body.grade);
+    let next_review_at = (Utc::now() + chrono::Duration::days(schedule.interval_days))
+        .format(SQLITE_DATETIME_FORMAT)
+        .to_string();
+
+    let saved = sqlx::query(
+        "INSERT INTO review_state (session, question_id, ease_factor, interval_days, repetitions, next_review_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, datetime('now')) \
+         ON CONFLICT (session, question_id) DO UPDATE SET ease_factor = excluded.ease_factor, interval_days = excluded.interval_days, repetitions = excluded.repetitions, next_review_at = excluded.next_review_at, updated_at = excluded.updated_at",
+    )
+    .bind(session)
+    .bind(body.question_id)
+    .bind(schedule.ease_factor)
+    .bind(schedule.interval_days)
+    .bind(schedule.repetitions)
+    .bind(&next_review_at)
+    .execute(&mut *tx)
+    .await;
+    let failed = |e: sqlx::Error| ApiError::internal_from("Failed to record review", e);
+    saved.map_err(failed)?;
+    tx.commit().await.map_err(failed)?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "session": session,
+        "question_id": body.question_id,
+        "grade": body.grade,
+        "repetitions": schedule.repetitions,
+        "interval_days": schedule.interval_days,
+        "ease_factor": schedule.ease_factor,
+        "next_review_at": rfc3339(Some(next_review_at)),
+    })))
+}
+
+/// Questions of a session that are due for review, most overdue first
+///
+/// `quiz_id` is a live quiz the question is in (the lowest id, for a bank
+/// question attached to several).
+pub async fn due_reviews(
+    pool: web::Data<SqlitePool>,
+    query: web::Query<DueReviewsQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = pool.get_ref();
+    let session = review_session(&query.session).map_err(ApiError::BadRequest)?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_LIMIT)
+        .clamp(1, MAX_PAGE_LIMIT);
+
+    let sql = format!(
+        "SELECT {}, r.repetitions, r.interval_days, r.ease_factor, r.next_review_at, \
+         (SELECT MIN(qq.quiz_id) FROM quiz_questions qq WHERE qq.question_id = questions.id AND qq.quiz_id IN (SELECT id FROM quizzes WHERE deleted_at IS NULL)) AS quiz_id \
+         FROM questions JOIN review_state r ON r.question_id = questions.id \
+         WHERE r.session = ? AND r.next_review_at <= datetime('now') AND questions.id IN (SELECT question_id FROM quiz_questions WHERE quiz_id IN (SELECT id FROM quizzes WHERE deleted_at IS NULL)) \
+         ORDER BY r.next_review_at, questions.id LIMIT ?",
+        QUESTION_COLUMNS
+    );
+    let rows = sqlx::query(&sql)
+        .bind(session)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| ApiError::internal_from("Failed to fetch due reviews", e))?;
+
+    let due: Vec<JsonValue> = rows
+        .iter()
+        .map(|r| {
+            json!({
+                "quiz_id": r.try_get::<i64, _>("quiz_id").unwrap_or(0),
+                "question": question_from_row(r),
+                "repetitions": r.try_get::<i64, _>("repetitions").unwrap_or(0),
+                "interval_days": r.try_get::<i64, _>("interval_days").unwrap_or(0),
+                "ease_factor": r.try_get::<f64, _>("ease_factor").unwrap_or(srs::INITIAL_EASE_FACTOR),
+                "next_review_at": rfc3339(r.try_get("next_review_at").ok()),
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(due))
+}
+
+/// Hepburn romaji for the kana in `text`; everything else is passed through
+pub async fn romaji(body: web::Json<RomajiRequest>) -> impl Responder {
+    HttpResponse::Ok().json(json!({"romaji": tools::kana_to_romaji(&body.text)}))
+}
+
+/// Readiness check: verifies the database answers a trivial query
+///
+/// While `db::spawn_monitor` has the connection marked as lost, answers 503
+/// straight away instead of waiting on the pool; the monitor notices when
+/// the database is back.
+pub async fn health(pool: web::Data<SqlitePool>, db_health: web::Data<DbHealth>) -> impl Responder {
+    let up = db_health.is_up()
+        && sqlx::query("SELECT 1")
+            .execute(pool.get_ref())
+            .await
+            .is_ok();
+    if !up {
+        db_health.record(false);
+        return HttpResponse::ServiceUnavailable()
+            .json(json!({"status": "degraded", "db": "down"}));
+    }
+    HttpResponse::Ok().json(json!({"status": "ok", "db": "up"}))
+}
+
+/// Error handler for `web::Json` extraction failures
+///
+/// Replaces Actix's plain-text body with our `{ "error": ... }` envelope while
+/// keeping the status Actix picked (400 for malformed or mistyped JSON).
+pub fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    let status = err.status_code();
+    let (code, message) = match &err {
+        JsonPayloadError::OverflowKnownLength { limit, .. }
+        | JsonPayloadError::Overflow { limit } => (
+            "payload_too_large",
+            format!("Request body is too large (limit is {} bytes)", limit),
+        ),
+        _ => ("invalid_json", format!("Invalid JSON body: {}", err)),
+    };
+    let body = json!({ "error": message, "code": code });
+    InternalError::from_response(err, HttpResponse::build(status).json(body)).into()
+}
+
+/// Quizzes loaded per query by `export_all`
+const EXPORT_BATCH_SIZE: i64 = 100;
+
+/// Every non-deleted quiz (drafts included), streamed as NDJSON: one line
+/// per quiz in the shape of `GET /api/quizzes/{id}`, in id order.
+///
+/// Quizzes are loaded `EXPORT_BATCH_SIZE` at a time as the client reads,
+/// so memory stays bounded, and each batch borrows a pool connection only
+/// while it is queried. A database error mid-stream ends the body early; it
+/// is logged, since the 200 has already been sent. Bank questions attached
+/// to no quiz are not included.
+pub async fn export_all(pool: web::Data<SqlitePool>) -> HttpResponse {
+    let pool = pool.get_ref().clone();
+    let lines = futures_util::stream::try_unfold(Some(0), move |after_id| {
+        let pool = pool.clone();
+        async move {
+            let Some(after_id) = after_id else {
+                return Ok(None);
+            };
+            let quizzes = queries::fetch_quiz_batch(&pool, after_id, EXPORT_BATCH_SIZE)
+                .await
+                .map_err(|e| {
+                    log::error!("Export stopped after quiz {}: {}", after_id, e);
+                    actix_web::Error::from(ApiError::internal("Failed to fetch quizzes"))
+                })?;
+            let Some(last) = quizzes.last() else {
+                return Ok(None);
+            };
+            let next = (quizzes.len() as i64 == EXPORT_BATCH_SIZE).then_some(last.id as i64);
+
+            let mut batch = Vec::new();
+            for quiz in &quizzes {
+                serde_json::to_writer(&mut batch, quiz)
+                    .map_err(|e| ApiError::internal_from("Failed to serialize quiz", e))?;
+                batch.push(b'\n');
+            }
+            Ok::<_, actix_web::Error>(Some((web::Bytes::from(batch), next)))
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .insert_header(header::ContentDisposition::attachment("quizzes.ndjson"))
+        .streaming(lines)
+}
+
+/// Prometheus metrics: request counts and latencies per route, and the
+/// database pool's connection counts
+pub async fn metrics(
+    metrics: web::Data<Metrics>,
+    pool: web::Data<SqlitePool>,
+) -> Result<HttpResponse, ApiError> {
+    match metrics.render(pool.get_ref()) {
+        Ok(text) => Ok(HttpResponse::Ok()
+            .content_type("text/plain; version=0.0.4; charset=utf-8")
+            .body(text)),
+        Err(e) => Err(ApiError::internal_from("Failed to encode metrics", e)),
+    }
+}
+
+/// The generated OpenAPI document
+pub async fn openapi_json() -> impl Responder {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+/// Fallback for requests no route matches, in the JSON error envelope
+/// instead of Actix's empty 404
+pub async fn not_found(req: HttpRequest) -> impl Responder {
+    HttpResponse::NotFound()
+        .json(json!({"error": "Not found", "code": "not_found", "path": req.path()}))
 }
 
 pub fn config(cfg: &mut web::ServiceConfig) {
+    // Health check lives outside /api so load balancers don't hit application routes
+    cfg.route("/health", web::get().to(health));
+    // the UI's assets are relative to /swagger/
+    cfg.service(web::redirect("/swagger", "/swagger/"));
+    cfg.service(openapi::swagger_ui());
+    cfg.route("/metrics", web::get().to(metrics));
+    cfg.default_service(web::to(not_found));
+
     cfg.service(
         web::scope("/api")
             .route("/quizzes", web::get().to(list_quizzes))
             .route("/quizzes", web::post().to(create_quiz))
+            .route("/quizzes/import", web::post().to(import_quizzes))
+            .route("/quizzes/merge", web::post().to(merge_quizzes))
+            .route("/quizzes/bulk-delete", web::post().to(bulk_delete_quizzes))
+            .route("/quizzes/summaries", web::get().to(quiz_summaries))
+            .route("/quizzes/daily", web::get().to(daily_quiz))
             .route("/quizzes/{id}", web::get().to(get_quiz))
+            .route("/quizzes/{id}", web::put().to(update_quiz))
+            .route("/quizzes/{id}", web::patch().to(patch_quiz))
+            .route("/quizzes/{id}", web::delete().to(delete_quiz))
+            .route("/quizzes/{id}/restore", web::post().to(restore_quiz))
+            .route("/quizzes/{id}/publish", web::post().to(publish_quiz))
+            .route("/quizzes/{id}/favorite", web::post().to(favorite_quiz))
+            .route("/quizzes/{id}/favorite", web::delete().to(unfavorite_quiz))
+            .route("/quizzes/{id}/duplicate", web::post().to(duplicate_quiz))
+            .route("/quizzes/{id}/variants", web::post().to(create_variants))
+            .route("/quizzes/{id}/practice", web::get().to(practice_quiz))
+            .route("/quizzes/{id}/random", web::get().to(random_quiz))
+            .route("/quizzes/{id}/distractors", web::get().to(quiz_distractors))
+            .route("/quizzes/{id}/questions", web::post().to(add_questions))
+            .route("/quizzes/{id}/questions", web::delete().to(clear_questions))
+            .route(
+                "/quizzes/{id}/import/csv",
+                web::post().to(import_questions_csv),
+            )
+            .route(
+                "/quizzes/{id}/questions/order",
+                web::put().to(reorder_questions),
+            )
+            .route(
+                "/quizzes/{id}/questions/attach",
+                web::post().to(attach_questions),
+            )
+            .route(
+                "/quizzes/{id}/questions/detach",
+                web::post().to(detach_questions),
+            )
+            .route(
+                "/quizzes/{quiz_id}/questions/{question_id}",
+                web::get().to(get_question),
+            )
+            .route(
+                "/quizzes/{quiz_id}/questions/{question_id}",
+                web::patch().to(update_question),
+            )
+            .route(
+                "/quizzes/{quiz_id}/questions/{question_id}",
+                web::delete().to(delete_question),
+            )
+            .route("/quizzes/{id}/submit", web::post().to(submit_quiz))
+            .route("/quizzes/{id}/attempts", web::get().to(list_attempts))
+            .route("/quizzes/{id}/leaderboard", web::get().to(leaderboard))
+            .route(
+                "/quizzes/{id}/question-stats",
+                web::get().to(question_stats),
+            )
+            .route("/quizzes/{id}/stats", web::get().to(quiz_stats))
+            .route("/quizzes/{id}/export", web::get().to(export_quiz))
+            .route("/quizzes/{id}/print", web::get().to(print_quiz))
+            .route("/openapi.json", web::get().to(openapi_json))
+            .route("/export/all", web::get().to(export_all))
+            .route("/import/all", web::post().to(import_all))
+            .route("/questions", web::post().to(create_bank_questions))
+            .route("/search", web::get().to(search))
+            .route("/stats", web::get().to(stats))
+            .route("/reviews", web::post().to(record_review))
+            .route("/reviews/due", web::get().to(due_reviews))
+            .route("/tools/romaji", web::post().to(romaji))
             .route("/tests", web::post().to(create_test))
             .route("/tests/{id}", web::get().to(get_test)),
     );
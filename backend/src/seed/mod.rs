@@ -0,0 +1,162 @@
+use crate::models::{CreateQuizRequest, QuizStatus};
+use crate::queries::insert_quiz;
+use serde_json::json;
+use sqlx::SqlitePool;
+
+/// Sample quizzes inserted by `seed`, in the same shape as a
+/// `POST /api/quizzes` body
+fn sample_quizzes() -> serde_json::Value {
+    json!([
+        {
+            "title": "N5 Everyday Nouns",
+            "description": "Common nouns from daily life",
+            "jlpt_level": "N5",
+            "tags": ["vocab", "nouns"],
+            "questions": [
+                {
+                    "text": "What does 水 (みず) mean?",
+                    "options": ["Fire", "Water", "Tree", "Mountain"],
+                    "correct_answer": 1,
+                    "reading": "みず",
+                    "explanation": "水 is water; fire is 火 (ひ)",
+                    "example_sentence": "水を飲みます。",
+                    "example_reading": "みずをのみます。",
+                    "difficulty": 1
+                },
+                {
+                    "text": "What does 学校 mean?",
+                    "options": ["Hospital", "Station", "School", "Library"],
+                    "correct_answer": 2,
+                    "reading": "がっこう",
+                    "difficulty": 1
+                },
+                {
+                    "text": "What does 友達 mean?",
+                    "options": ["Friend", "Teacher", "Family", "Neighbour"],
+                    "correct_answer": 0,
+                    "reading": "ともだち",
+                    "difficulty": 2
+                },
+                {
+                    "text": "Type the reading of 電車 in hiragana",
+                    "question_type": "text",
+                    "options": [],
+                    "answer_text": ["でんしゃ"],
+                    "difficulty": 2
+                }
+            ]
+        },
+        {
+            "title": "N4 Common Verbs",
+            "description": "Verbs that come up in everyday conversation",
+            "jlpt_level": "N4",
+            "tags": ["vocab", "verbs"],
+            "questions": [
+                {
+                    "text": "What does 届ける (とどける) mean?",
+                    "options": ["To deliver", "To borrow", "To forget", "To repair"],
+                    "correct_answer": 0,
+                    "reading": "とどける",
+                    "difficulty": 3
+                },
+                {
+                    "text": "What does 集める mean?",
+                    "options": ["To throw away", "To collect", "To divide", "To hide"],
+                    "correct_answer": 1,
+                    "reading": "あつめる",
+                    "difficulty": 3
+                },
+                {
+                    "text": "Which verbs mean \"to return (home)\"?",
+                    "question_type": "multiple",
+                    "options": ["帰る", "戻る", "通る", "登る"],
+                    "correct_answers": [0, 1],
+                    "explanation": "帰る is going back home; 戻る is returning to where you were",
+                    "difficulty": 3
+                }
+            ]
+        },
+        {
+            "title": "N3 Adjectives",
+            "description": "い- and な-adjectives at the N3 level",
+            "jlpt_level": "N3",
+            "tags": ["vocab", "adjectives"],
+            "questions": [
+                {
+                    "text": "What does 詳しい (くわしい) mean?",
+                    "options": ["Detailed", "Dangerous", "Thin", "Noisy"],
+                    "correct_answer": 0,
+                    "reading": "くわしい",
+                    "example_sentence": "詳しい説明をお願いします。",
+                    "example_reading": "くわしいせつめいをおねがいします。",
+                    "difficulty": 4
+                },
+                {
+                    "text": "What does 退屈 (たいくつ) mean?",
+                    "options": ["Exciting", "Boring", "Strict", "Polite"],
+                    "correct_answer": 1,
+                    "reading": "たいくつ",
+                    "difficulty": 4
+                },
+                {
+                    "text": "What does 貴重 mean?",
+                    "options": ["Heavy", "Cheap", "Valuable", "Ordinary"],
+                    "correct_answer": 2,
+                    "reading": "きちょう",
+                    "difficulty": 5
+                }
+            ]
+        }
+    ])
+}
+
+/// Insert the sample quizzes (published, so they show up in listings) and
+/// return the id and owner token of each one added.
+///
+/// Does nothing and returns none when the database already has quizzes,
+/// even deleted ones, so running it twice is harmless and real data is
+/// never mixed with samples.
+pub async fn run(pool: &SqlitePool) -> Result<Vec<(i64, String)>, String> {
+    let existing: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM quizzes")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Failed to count quizzes: {}", e))?;
+    if existing > 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut quizzes: Vec<CreateQuizRequest> = serde_json::from_value(sample_quizzes())
+        .map_err(|e| format!("Invalid sample quiz: {}", e))?;
+    for quiz in &mut quizzes {
+        quiz.normalize();
+        for (index, question) in quiz.questions.iter().enumerate() {
+            question.validate().map_err(|e| {
+                format!(
+                    "Invalid sample question {} of '{}': {}",
+                    index, quiz.title, e.message
+                )
+            })?;
+        }
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let mut seeded = Vec::with_capacity(quizzes.len());
+    for quiz in &quizzes {
+        let (id, owner_token) = insert_quiz(&mut tx, quiz).await?;
+        sqlx::query("UPDATE quizzes SET status = ? WHERE id = ?")
+            .bind(QuizStatus::Published.as_str())
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to publish '{}': {}", quiz.title, e))?;
+        seeded.push((id, owner_token));
+    }
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit sample quizzes: {}", e))?;
+
+    Ok(seeded)
+}
@@ -0,0 +1,138 @@
+//! SM-2 spaced-repetition scheduling.
+//!
+//! After each review the learner grades their recall from 0 (blackout) to 5
+//! (perfect). A grade of 3 or more counts as remembered and pushes the next
+//! review further out (1 day, 6 days, then the previous interval times the ease
+//! factor); anything lower starts the item over at 1 day. The ease factor moves
+//! with every grade and never drops below 1.3.
+
+/// Highest grade a review can be given
+pub const MAX_GRADE: i32 = 5;
+
+/// Lowest grade that still counts as remembered
+const PASSING_GRADE: i32 = 3;
+
+/// Ease factor a new item starts with
+pub const INITIAL_EASE_FACTOR: f64 = 2.5;
+
+/// The ease factor is never lowered past this, so hard items still progress
+const MIN_EASE_FACTOR: f64 = 1.3;
+
+/// Where an item stands in its review schedule
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Schedule {
+    /// Successful reviews in a row
+    pub repetitions: i64,
+    /// Days until the next review
+    pub interval_days: i64,
+    pub ease_factor: f64,
+}
+
+impl Default for Schedule {
+    /// An item that has never been reviewed
+    fn default() -> Self {
+        Schedule {
+            repetitions: 0,
+            interval_days: 0,
+            ease_factor: INITIAL_EASE_FACTOR,
+        }
+    }
+}
+
+impl Schedule {
+    /// The schedule after a review graded `grade` (clamped to 0..=5)
+    pub fn review(self, grade: i32) -> Schedule {
+        let grade = grade.clamp(0, MAX_GRADE);
+
+        let (repetitions, interval_days) = if grade < PASSING_GRADE {
+            (0, 1)
+        } else {
+            let interval = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => ((self.interval_days as f64) * self.ease_factor).round() as i64,
+            };
+            (self.repetitions + 1, interval.max(1))
+        };
+
+        let miss = f64::from(MAX_GRADE - grade);
+        let ease_factor =
+            (self.ease_factor + 0.1 - miss * (0.08 + miss * 0.02)).max(MIN_EASE_FACTOR);
+
+        Schedule {
+            repetitions,
+            interval_days,
+            ease_factor,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn passing_reviews_space_out_1_then_6_then_by_the_ease_factor() {
+        let first = Schedule::default().review(5);
+        assert_eq!((first.repetitions, first.interval_days), (1, 1));
+        assert_close(first.ease_factor, 2.6);
+
+        let second = first.review(5);
+        assert_eq!((second.repetitions, second.interval_days), (2, 6));
+        assert_close(second.ease_factor, 2.7);
+
+        // 6 days times the ease factor of 2.7, rounded
+        let third = second.review(5);
+        assert_eq!((third.repetitions, third.interval_days), (3, 16));
+        assert_close(third.ease_factor, 2.8);
+    }
+
+    #[test]
+    fn the_grade_moves_the_ease_factor() {
+        let start = Schedule::default();
+        assert_close(start.review(4).ease_factor, 2.5);
+        assert_close(start.review(3).ease_factor, 2.36);
+        assert_close(start.review(0).ease_factor, 1.7);
+    }
+
+    #[test]
+    fn a_failed_review_starts_over_at_one_day() {
+        let learned = Schedule {
+            repetitions: 4,
+            interval_days: 40,
+            ease_factor: 2.5,
+        };
+        let failed = learned.review(2);
+        assert_eq!((failed.repetitions, failed.interval_days), (0, 1));
+        assert_close(failed.ease_factor, 2.18);
+
+        // and the next pass counts as the first again
+        let relearned = failed.review(4);
+        assert_eq!((relearned.repetitions, relearned.interval_days), (1, 1));
+    }
+
+    #[test]
+    fn the_ease_factor_never_drops_below_the_floor() {
+        let mut schedule = Schedule::default();
+        for _ in 0..10 {
+            schedule = schedule.review(0);
+        }
+        assert_close(schedule.ease_factor, MIN_EASE_FACTOR);
+    }
+
+    #[test]
+    fn grades_outside_0_to_5_are_clamped() {
+        let start = Schedule::default();
+        assert_eq!(start.review(9), start.review(5));
+        assert_eq!(start.review(-3), start.review(0));
+    }
+}
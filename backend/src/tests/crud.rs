@@ -0,0 +1,272 @@
+use super::{create_quiz, quiz_json, send, state, state_with, test_config};
+use crate::build_app;
+use actix_web::http::{header, StatusCode};
+use actix_web::test::{self, TestRequest};
+
+#[actix_web::test]
+async fn create_get_list_delete() {
+    let app = test::init_service(build_app(&state().await)).await;
+
+    let (id, token) = create_quiz(&app, quiz_json("Colours", 2)).await;
+
+    let (status, quiz) = send(
+        &app,
+        TestRequest::get().uri(&format!("/api/quizzes/{}", id)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(quiz["title"], "Colours");
+    assert_eq!(quiz["questions"].as_array().map(Vec::len), Some(2));
+    assert_eq!(quiz["questions"][1]["options"][0], "Right 1");
+
+    // new quizzes are drafts, which the listing only includes on request
+    let (status, page) = send(
+        &app,
+        TestRequest::get().uri("/api/quizzes?include_drafts=true"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(page["total"], 1);
+    assert_eq!(page["quizzes"][0]["id"], id);
+
+    let (status, _) = send(
+        &app,
+        TestRequest::delete()
+            .uri(&format!("/api/quizzes/{}", id))
+            .insert_header(("X-Owner-Token", token)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, body) = send(
+        &app,
+        TestRequest::get().uri(&format!("/api/quizzes/{}", id)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "quiz_not_found");
+    let (_, page) = send(
+        &app,
+        TestRequest::get().uri("/api/quizzes?include_drafts=true"),
+    )
+    .await;
+    assert_eq!(page["total"], 0);
+}
+
+#[actix_web::test]
+async fn a_failing_question_insert_leaves_no_quiz_behind() {
+    let state = state().await;
+    // make the database reject one question part-way through the create
+    sqlx::query(
+        "CREATE TRIGGER reject_question BEFORE INSERT ON questions WHEN NEW.prompt = 'Question 2' BEGIN SELECT RAISE(ABORT, 'rejected'); END",
+    )
+    .execute(&state.pool)
+    .await
+    .expect("trigger is created");
+    let app = test::init_service(build_app(&state)).await;
+
+    let (status, body) = send(
+        &app,
+        TestRequest::post()
+            .uri("/api/quizzes")
+            .set_json(quiz_json("Half written", 4)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(body["error"].is_string(), "{}", body);
+
+    for table in ["quizzes", "questions", "quiz_questions"] {
+        let (rows,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(&state.pool)
+            .await
+            .expect("count");
+        assert_eq!(rows, 0, "{} has rows left", table);
+    }
+}
+
+#[actix_web::test]
+async fn a_deleted_quiz_leaves_the_list_until_restored() {
+    let app = test::init_service(build_app(&state().await)).await;
+    let (kept, _) = create_quiz(&app, quiz_json("Kept", 1)).await;
+    let (id, token) = create_quiz(&app, quiz_json("Undone", 1)).await;
+    let listed = |page: &serde_json::Value| -> Vec<i64> {
+        page["quizzes"]
+            .as_array()
+            .expect("quizzes")
+            .iter()
+            .map(|quiz| quiz["id"].as_i64().expect("id"))
+            .collect()
+    };
+
+    let (status, _) = send(
+        &app,
+        TestRequest::delete()
+            .uri(&format!("/api/quizzes/{}", id))
+            .insert_header(("X-Owner-Token", token.as_str())),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, page) = send(
+        &app,
+        TestRequest::get().uri("/api/quizzes?include_drafts=true"),
+    )
+    .await;
+    assert_eq!(listed(&page), vec![kept]);
+
+    let (status, restored) = send(
+        &app,
+        TestRequest::post()
+            .uri(&format!("/api/quizzes/{}/restore", id))
+            .insert_header(("X-Owner-Token", token.as_str())),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", restored);
+    assert_eq!(restored["title"], "Undone");
+
+    let (_, page) = send(
+        &app,
+        TestRequest::get().uri("/api/quizzes?include_drafts=true"),
+    )
+    .await;
+    assert_eq!(listed(&page), vec![id, kept]);
+    let (status, _) = send(
+        &app,
+        TestRequest::get().uri(&format!("/api/quizzes/{}", id)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn created_at_is_rfc3339_utc() {
+    let state = state().await;
+    let app = test::init_service(build_app(&state)).await;
+    let (id, _) = create_quiz(&app, quiz_json("Timestamps", 1)).await;
+
+    let (_, quiz) = send(
+        &app,
+        TestRequest::get().uri(&format!("/api/quizzes/{}", id)),
+    )
+    .await;
+    let created_at = quiz["created_at"].as_str().expect("created_at");
+    assert!(created_at.ends_with('Z'), "{}", created_at);
+    let parsed = chrono::DateTime::parse_from_rfc3339(created_at).expect("RFC 3339");
+    assert_eq!(parsed.offset().local_minus_utc(), 0);
+
+    // stored values with an offset come out in UTC too
+    for (stored, wire) in [
+        ("2024-05-01 09:30:00", "2024-05-01T09:30:00Z"),
+        ("2024-05-01T18:30:00+09:00", "2024-05-01T09:30:00Z"),
+    ] {
+        sqlx::query("UPDATE quizzes SET created_at = ? WHERE id = ?")
+            .bind(stored)
+            .bind(id)
+            .execute(&state.pool)
+            .await
+            .expect("created_at is set");
+        let (_, quiz) = send(
+            &app,
+            TestRequest::get().uri(&format!("/api/quizzes/{}", id)),
+        )
+        .await;
+        assert_eq!(quiz["created_at"], wire);
+    }
+}
+
+#[actix_web::test]
+async fn unique_title_follows_renames() {
+    let app = test::init_service(build_app(&state().await)).await;
+    let (id, token) = create_quiz(&app, quiz_json("Alpha", 1)).await;
+    let create_unique = |title: &str| {
+        TestRequest::post()
+            .uri("/api/quizzes?unique_title=true")
+            .set_json(quiz_json(title, 1))
+    };
+
+    let (status, renamed) = send(
+        &app,
+        TestRequest::patch()
+            .uri(&format!("/api/quizzes/{}", id))
+            .insert_header(("X-Owner-Token", token.as_str()))
+            .set_json(serde_json::json!({ "title": "Ｂｅｔａ" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", renamed);
+
+    let (status, conflict) = send(&app, create_unique(" beta ")).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{}", conflict);
+    assert_eq!(conflict["code"], "duplicate_title");
+    assert_eq!(conflict["existing_id"], id);
+
+    // the old title is free again
+    let (status, created) = send(&app, create_unique("alpha")).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", created);
+}
+
+#[actix_web::test]
+async fn a_quiz_is_revalidated_with_its_weak_etag() {
+    let app = test::init_service(build_app(&state().await)).await;
+    let (id, _) = create_quiz(&app, quiz_json("Cached", 1)).await;
+    let uri = format!("/api/quizzes/{}", id);
+
+    let res = test::call_service(&app, TestRequest::get().uri(&uri).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let etag = res.headers().get(header::ETAG).expect("ETag").clone();
+    // W/ and a quoted hex SHA-256
+    assert_eq!(etag.len(), 2 + 2 + 64, "{:?}", etag);
+    assert!(etag.to_str().unwrap().starts_with("W/\""), "{:?}", etag);
+
+    let res = test::call_service(
+        &app,
+        TestRequest::get()
+            .uri(&uri)
+            .insert_header((header::IF_NONE_MATCH, etag.clone()))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(res.headers().get(header::ETAG), Some(&etag));
+}
+
+#[actix_web::test]
+async fn concurrent_creates_with_a_unique_title_conflict_cleanly() {
+    // a file, unlike the shared in-memory database, lets connections write
+    // concurrently the way a real deployment does
+    let path = std::env::temp_dir().join(format!("unique-title-{}.db", uuid::Uuid::new_v4()));
+    let mut config = test_config();
+    config.database.url = format!("sqlite://{}?mode=rwc", path.display());
+    let app = test::init_service(build_app(&state_with(config).await)).await;
+    let create = || {
+        send(
+            &app,
+            TestRequest::post()
+                .uri("/api/quizzes?unique_title=true")
+                .set_json(quiz_json("Raced", 20)),
+        )
+    };
+
+    let results = futures_util::future::join_all((0..4).map(|_| create())).await;
+    let created = results
+        .iter()
+        .filter(|(status, _)| *status == StatusCode::CREATED)
+        .count();
+    assert_eq!(created, 1, "{:?}", results);
+    for (status, body) in &results {
+        assert!(
+            matches!(*status, StatusCode::CREATED | StatusCode::CONFLICT),
+            "{}",
+            body
+        );
+    }
+
+    let (_, page) = send(
+        &app,
+        TestRequest::get().uri("/api/quizzes?include_drafts=true&q=raced"),
+    )
+    .await;
+    assert_eq!(page["total"], 1, "{}", page);
+
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+    }
+}
@@ -0,0 +1,48 @@
+use super::test_config;
+use crate::{db, queries};
+use std::time::Duration;
+
+/// Counts to a billion, which takes far longer than the limits used here
+const SLOW_QUERY: &str = "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < 1000000000) SELECT count(*) FROM n";
+
+#[actix_web::test]
+async fn a_query_past_the_checkout_timeout_is_interrupted() {
+    let mut config = test_config();
+    config.database.checkout_timeout = Duration::from_millis(100);
+    let pool = db::connect(&config.database)
+        .await
+        .expect("in-memory database opens");
+
+    let err = sqlx::query_as::<_, (i64,)>(SLOW_QUERY)
+        .fetch_one(&pool)
+        .await
+        .expect_err("the query is cut short");
+    assert!(err.to_string().contains("interrupted"), "{}", err);
+
+    // the clock restarts on the next checkout
+    let (one,): (i64,) = sqlx::query_as("SELECT 1")
+        .fetch_one(&pool)
+        .await
+        .expect("a fresh checkout has the whole budget");
+    assert_eq!(one, 1);
+}
+
+#[actix_web::test]
+async fn quizzes_from_before_title_keys_get_one_on_startup() {
+    let pool = db::connect(&test_config().database)
+        .await
+        .expect("in-memory database opens");
+    db::init_db(&pool).await.expect("migrations apply");
+    // as left by migration 0010 for a quiz that already existed
+    let id = sqlx::query("INSERT INTO quizzes (title) VALUES ('ＪＬＰＴ N3')")
+        .execute(&pool)
+        .await
+        .expect("quiz inserts")
+        .last_insert_rowid();
+
+    db::init_db(&pool).await.expect("init_db reruns");
+    let found = queries::quiz_id_with_title(&pool, " jlpt n3")
+        .await
+        .expect("lookup runs");
+    assert_eq!(found, Some(id));
+}
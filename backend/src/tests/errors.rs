@@ -0,0 +1,197 @@
+use super::{send, state, state_with, test_config};
+use crate::build_app;
+use actix_web::http::{header, StatusCode};
+use actix_web::test::{self, TestRequest};
+use actix_web::{web, HttpResponse};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[actix_web::test]
+async fn malformed_json_gets_the_error_envelope() {
+    let app = test::init_service(build_app(&state().await)).await;
+
+    for body in [r#"{"title": "Broken", "questions": ["#, r#"{"title": 5}"#] {
+        let (status, error) = send(
+            &app,
+            TestRequest::post()
+                .uri("/api/quizzes")
+                .insert_header((header::CONTENT_TYPE, "application/json"))
+                .set_payload(body),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+        assert!(error["error"].is_string(), "{}", error);
+        assert_eq!(error["code"], "invalid_json");
+    }
+}
+
+#[actix_web::test]
+async fn unknown_paths_get_a_json_404() {
+    let app = test::init_service(build_app(&state().await)).await;
+
+    for path in ["/nope", "/api/quizzes/1/nope"] {
+        let (status, body) = send(&app, TestRequest::get().uri(path)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{}", path);
+        assert_eq!(body["error"], "Not found");
+        assert_eq!(body["path"], path);
+    }
+
+    // the API itself is still routed
+    let (status, _) = send(&app, TestRequest::get().uri("/api/quizzes")).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+/// A create body of exactly `len` bytes, padded out in the title
+fn body_of_len(len: usize) -> String {
+    let empty = r#"{"title": "", "questions": []}"#;
+    let title = "x".repeat(len - empty.len());
+    format!(r#"{{"title": "{}", "questions": []}}"#, title)
+}
+
+#[actix_web::test]
+async fn a_body_just_over_the_limit_is_a_json_413() {
+    let mut config = test_config();
+    config.max_body_bytes = 1024;
+    let app = test::init_service(build_app(&state_with(config).await)).await;
+    let create = |body: String| {
+        TestRequest::post()
+            .uri("/api/quizzes")
+            .insert_header((header::CONTENT_TYPE, "application/json"))
+            .set_payload(body)
+    };
+
+    let (status, body) = send(&app, create(body_of_len(1025))).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(body["code"], "payload_too_large");
+    assert_eq!(
+        body["error"],
+        "Request body is too large (limit is 1024 bytes)"
+    );
+
+    // at the limit the body is read, and rejected for having no questions
+    let (status, body) = send(&app, create(body_of_len(1024))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+}
+
+#[actix_web::test]
+async fn a_slow_handler_is_cancelled_with_a_json_504() {
+    let mut config = test_config();
+    config.request_timeout = Some(Duration::from_millis(50));
+    let finished = Arc::new(AtomicBool::new(false));
+    let handler_finished = finished.clone();
+    let app = test::init_service(build_app(&state_with(config).await).route(
+        "/slow",
+        web::get().to(move || {
+            let finished = handler_finished.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                finished.store(true, Ordering::SeqCst);
+                HttpResponse::Ok().finish()
+            }
+        }),
+    ))
+    .await;
+
+    let (status, body) = send(&app, TestRequest::get().uri("/slow")).await;
+    assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(body["error"], "Request timed out");
+
+    // the handler was dropped rather than left running
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(!finished.load(Ordering::SeqCst));
+
+    // fast requests are unaffected
+    let (status, _) = send(&app, TestRequest::get().uri("/api/quizzes")).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn errors_are_translated_by_their_code() {
+    let app = test::init_service(build_app(&state().await)).await;
+    let in_japanese = |req: TestRequest| req.insert_header((header::ACCEPT_LANGUAGE, "ja"));
+
+    // a message naming a value still has its own code, and keeps its English
+    // text since there is no fixed translation of it
+    let (status, body) = send(
+        &app,
+        in_japanese(TestRequest::get().uri("/api/quizzes?level=N9")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_jlpt_level");
+    assert_eq!(
+        body["error"],
+        "Invalid level 'N9', expected one of N5, N4, N3, N2, N1"
+    );
+
+    let (status, body) = send(
+        &app,
+        in_japanese(TestRequest::get().uri("/api/quizzes/999")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "quiz_not_found");
+    assert_eq!(body["error"], "クイズが見つかりません");
+
+    // entries of an errors list are translated by their own code
+    let (status, body) = send(
+        &app,
+        in_japanese(
+            TestRequest::post()
+                .uri("/api/quizzes")
+                .set_json(serde_json::json!({
+                    "title": "",
+                    "questions": [{
+                        "text": "犬",
+                        "options": [],
+                    "question_type": "reading",
+                        "answer_text": ["いぬ"]
+                    }]
+                })),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_quiz");
+    let errors = body["errors"].as_array().unwrap();
+    let entry = |code: &str| errors.iter().find(|e| e["code"] == code).unwrap().clone();
+    assert_eq!(
+        entry("title_required")["message"],
+        "タイトルを入力してください"
+    );
+    assert_eq!(
+        entry("reading_required")["message"],
+        "読みの問題には reading が必要です"
+    );
+}
+
+#[actix_web::test]
+async fn swagger_ui_is_served_without_a_cdn() {
+    let app = test::init_service(build_app(&state().await)).await;
+
+    let res = test::call_service(&app, TestRequest::get().uri("/swagger").to_request()).await;
+    assert!(res.status().is_redirection());
+    assert_eq!(res.headers().get(header::LOCATION).unwrap(), "/swagger/");
+
+    let res = test::call_service(&app, TestRequest::get().uri("/swagger/").to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let page = test::read_body(res).await;
+    let page = std::str::from_utf8(&page).unwrap();
+    assert!(page.contains("swagger-ui-bundle.js"), "{}", page);
+    assert!(!page.contains("https://"), "{}", page);
+
+    // the page's config points at the spec this server generates
+    let res = test::call_service(
+        &app,
+        TestRequest::get()
+            .uri("/swagger/swagger-initializer.js")
+            .to_request(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let script = test::read_body(res).await;
+    assert!(std::str::from_utf8(&script)
+        .unwrap()
+        .contains("/api/openapi.json"));
+}
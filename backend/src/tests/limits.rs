@@ -0,0 +1,78 @@
+use super::{create_quiz, quiz_json, send, state};
+use crate::build_app;
+use crate::routes::MAX_QUESTIONS;
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use serde_json::json;
+
+#[actix_web::test]
+async fn a_quiz_holds_at_most_max_questions() {
+    let app = test::init_service(build_app(&state().await)).await;
+
+    let (id, token) = create_quiz(&app, quiz_json("Full", MAX_QUESTIONS)).await;
+    let (_, quiz) = send(
+        &app,
+        TestRequest::get().uri(&format!("/api/quizzes/{}", id)),
+    )
+    .await;
+    assert_eq!(
+        quiz["questions"].as_array().map(Vec::len),
+        Some(MAX_QUESTIONS)
+    );
+
+    let too_many = format!(
+        "A quiz may have at most {} questions, got {}",
+        MAX_QUESTIONS,
+        MAX_QUESTIONS + 1
+    );
+    let (status, body) = send(
+        &app,
+        TestRequest::post()
+            .uri("/api/quizzes")
+            .set_json(quiz_json("Over", MAX_QUESTIONS + 1)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["errors"][0]["field"], "questions");
+    assert_eq!(body["errors"][0]["message"], too_many.as_str());
+
+    let (status, body) = send(
+        &app,
+        TestRequest::put()
+            .uri(&format!("/api/quizzes/{}", id))
+            .insert_header(("X-Owner-Token", token))
+            .set_json(quiz_json("Full", MAX_QUESTIONS + 1)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    assert!(body.to_string().contains(&too_many), "{}", body);
+
+    let (status, body) = send(
+        &app,
+        TestRequest::post()
+            .uri("/api/quizzes/import")
+            .set_json(json!([quiz_json("Imported", MAX_QUESTIONS + 1)])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    assert!(body.to_string().contains(&too_many), "{}", body);
+}
+
+#[actix_web::test]
+async fn a_quiz_needs_a_question() {
+    let app = test::init_service(build_app(&state().await)).await;
+
+    let (status, body) = send(
+        &app,
+        TestRequest::post()
+            .uri("/api/quizzes")
+            .set_json(quiz_json("Empty", 0)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["errors"][0]["field"], "questions");
+    assert_eq!(
+        body["errors"][0]["message"],
+        "A quiz needs at least one question"
+    );
+}
@@ -0,0 +1,280 @@
+use super::{create_quiz, quiz_json, send, state};
+use crate::build_app;
+use actix_web::http::{header, StatusCode};
+use actix_web::test::{self, TestRequest};
+use serde_json::{json, Value as JsonValue};
+
+/// `quiz_json` with every question text prefixed by the title, so questions
+/// of different quizzes can be told apart
+fn labelled_quiz(title: &str, questions: usize) -> JsonValue {
+    let mut body = quiz_json(title, questions);
+    for (i, question) in body["questions"]
+        .as_array_mut()
+        .expect("questions")
+        .iter_mut()
+        .enumerate()
+    {
+        question["text"] = json!(format!("{} {}", title, i));
+    }
+    body
+}
+
+/// The ids of a listed page, in order
+fn ids(page: &JsonValue) -> Vec<i64> {
+    page["quizzes"]
+        .as_array()
+        .expect("quizzes")
+        .iter()
+        .map(|quiz| quiz["id"].as_i64().expect("id"))
+        .collect()
+}
+
+#[actix_web::test]
+async fn listed_questions_are_grouped_under_their_quiz_newest_first() {
+    let state = state().await;
+    let app = test::init_service(build_app(&state)).await;
+    let a = create_quiz(&app, labelled_quiz("A", 2)).await.0;
+    let b = create_quiz(&app, labelled_quiz("B", 3)).await.0;
+    let c = create_quiz(&app, labelled_quiz("C", 1)).await.0;
+    // B is the newest and C the oldest, which isn't the id order
+    for (id, created_at) in [
+        (a, "2024-01-02 00:00:00"),
+        (b, "2024-01-03 00:00:00"),
+        (c, "2024-01-01 00:00:00"),
+    ] {
+        sqlx::query("UPDATE quizzes SET created_at = ? WHERE id = ?")
+            .bind(created_at)
+            .bind(id)
+            .execute(&state.pool)
+            .await
+            .expect("created_at is set");
+    }
+
+    let (status, page) = send(
+        &app,
+        TestRequest::get().uri("/api/quizzes?include_drafts=true"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", page);
+    let listed: Vec<(i64, Vec<String>)> = page["quizzes"]
+        .as_array()
+        .expect("quizzes")
+        .iter()
+        .map(|quiz| {
+            let texts = quiz["questions"]
+                .as_array()
+                .expect("questions")
+                .iter()
+                .map(|q| q["text"].as_str().expect("text").to_string())
+                .collect();
+            (quiz["id"].as_i64().expect("id"), texts)
+        })
+        .collect();
+    assert_eq!(
+        listed,
+        vec![
+            (b, vec!["B 0".into(), "B 1".into(), "B 2".into()]),
+            (a, vec!["A 0".into(), "A 1".into()]),
+            (c, vec!["C 0".into()]),
+        ]
+    );
+}
+
+#[actix_web::test]
+async fn the_title_filter_ignores_case() {
+    let app = test::init_service(build_app(&state().await)).await;
+    let upper = create_quiz(&app, quiz_json("JLPT N3 Vocabulary", 1))
+        .await
+        .0;
+    let lower = create_quiz(&app, quiz_json("jlpt n5 kanji", 1)).await.0;
+    create_quiz(&app, quiz_json("Cooking words", 1)).await;
+
+    let titles_matching =
+        |q: &str| TestRequest::get().uri(&format!("/api/quizzes?include_drafts=true&q={}", q));
+
+    let (status, page) = send(&app, titles_matching("Jlpt")).await;
+    assert_eq!(status, StatusCode::OK, "{}", page);
+    assert_eq!(ids(&page), vec![lower, upper]);
+    assert_eq!(page["total"], 2);
+
+    let (_, page) = send(&app, titles_matching("VOCAB")).await;
+    assert_eq!(ids(&page), vec![upper]);
+
+    // beyond ASCII: full-width letters and accented capitals fold too
+    let umlauts = create_quiz(&app, quiz_json("ÄRGER und Übung", 1)).await.0;
+    let full_width = create_quiz(&app, quiz_json("ＮＨＫ News", 1)).await.0;
+    let encoded =
+        |q: &str| -> String { url::form_urlencoded::byte_serialize(q.as_bytes()).collect() };
+    let (_, page) = send(&app, titles_matching(&encoded("ärger"))).await;
+    assert_eq!(ids(&page), vec![umlauts]);
+    let (_, page) = send(&app, titles_matching(&encoded("üBUNG"))).await;
+    assert_eq!(ids(&page), vec![umlauts]);
+    let (_, page) = send(&app, titles_matching("nhk")).await;
+    assert_eq!(ids(&page), vec![full_width]);
+    let (_, page) = send(&app, titles_matching(&encoded("ｎｈｋ"))).await;
+    assert_eq!(ids(&page), vec![full_width]);
+
+    // without `q` every quiz is listed
+    let (_, page) = send(
+        &app,
+        TestRequest::get().uri("/api/quizzes?include_drafts=true"),
+    )
+    .await;
+    assert_eq!(page["total"], 5);
+}
+
+#[actix_web::test]
+async fn a_large_listing_is_compressed_on_request() {
+    let app = test::init_service(build_app(&state().await)).await;
+    for i in 0..20 {
+        create_quiz(&app, labelled_quiz(&format!("Quiz {}", i), 20)).await;
+    }
+    let list = || TestRequest::get().uri("/api/quizzes?include_drafts=true&limit=100");
+
+    let plain = test::call_service(&app, list().to_request()).await;
+    assert!(plain.headers().get(header::CONTENT_ENCODING).is_none());
+    let plain = test::read_body(plain).await;
+
+    let gzipped = test::call_service(
+        &app,
+        list()
+            .insert_header((header::ACCEPT_ENCODING, "gzip"))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(gzipped.status(), StatusCode::OK);
+    assert_eq!(
+        gzipped.headers().get(header::CONTENT_ENCODING),
+        Some(&header::HeaderValue::from_static("gzip"))
+    );
+    let gzipped = test::read_body(gzipped).await;
+    assert!(
+        gzipped.len() < plain.len() / 4,
+        "{} bytes gzipped, {} plain",
+        gzipped.len(),
+        plain.len()
+    );
+}
+
+#[actix_web::test]
+async fn a_date_range_may_leave_either_end_open() {
+    let state = state().await;
+    let app = test::init_service(build_app(&state)).await;
+    let mut created = Vec::new();
+    for (title, created_at) in [
+        ("New year", "2024-01-01 00:00:00"),
+        ("Second", "2024-01-02 00:00:00"),
+        ("Third", "2024-01-03 00:00:00"),
+    ] {
+        let id = create_quiz(&app, quiz_json(title, 1)).await.0;
+        sqlx::query("UPDATE quizzes SET created_at = ? WHERE id = ?")
+            .bind(created_at)
+            .bind(id)
+            .execute(&state.pool)
+            .await
+            .expect("created_at is set");
+        created.push(id);
+    }
+    let [first, second, third] = created[..] else {
+        unreachable!()
+    };
+    let listed = |filters: &str| {
+        TestRequest::get().uri(&format!("/api/quizzes?include_drafts=true&{}", filters))
+    };
+
+    let (status, page) = send(&app, listed("created_after=2024-01-02T00:00:00Z")).await;
+    assert_eq!(status, StatusCode::OK, "{}", page);
+    assert_eq!(ids(&page), vec![third, second]);
+    assert_eq!(page["total"], 2);
+
+    let (_, page) = send(&app, listed("created_before=2024-01-02T00:00:00Z")).await;
+    assert_eq!(ids(&page), vec![first]);
+
+    // the same instant written with an offset
+    let (_, page) = send(&app, listed("created_after=2024-01-02T09:00:00%2B09:00")).await;
+    assert_eq!(ids(&page), vec![third, second]);
+
+    // combined with the title filter
+    let (_, page) = send(&app, listed("created_after=2024-01-02T00:00:00Z&q=third")).await;
+    assert_eq!(ids(&page), vec![third]);
+
+    let (status, body) = send(&app, listed("created_before=yesterday")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        body["error"]
+            .as_str()
+            .is_some_and(|e| e.starts_with("Invalid created_before 'yesterday'")),
+        "{}",
+        body
+    );
+}
+
+#[actix_web::test]
+async fn search_finds_questions_by_substring_and_follows_edits() {
+    let app = test::init_service(build_app(&state().await)).await;
+    let mut body = quiz_json("Food", 2);
+    body["questions"][0]["text"] = json!("りんごを食べます");
+    body["questions"][1]["text"] = json!("犬");
+    let (id, token) = create_quiz(&app, body).await;
+    let (status, published) = send(
+        &app,
+        TestRequest::patch()
+            .uri(&format!("/api/quizzes/{}", id))
+            .insert_header(("X-Owner-Token", token.as_str()))
+            .set_json(json!({ "status": "published" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", published);
+
+    let search = |q: &str| {
+        let q: String = url::form_urlencoded::byte_serialize(q.as_bytes()).collect();
+        let uri = format!("/api/search?q={}", q);
+        TestRequest::get().uri(&uri)
+    };
+    let found = |results: &JsonValue| -> Vec<String> {
+        results
+            .as_array()
+            .unwrap()
+            .iter()
+            .flat_map(|quiz| quiz["questions"].as_array().unwrap().clone())
+            .map(|question| question["text"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    // long enough for the index, in the middle of the text
+    let (status, results) = send(&app, search("ごを食")).await;
+    assert_eq!(status, StatusCode::OK, "{}", results);
+    assert_eq!(found(&results), ["りんごを食べます"]);
+    assert_eq!(
+        results[0]["questions"][0]["highlighted_text"],
+        "りん<mark>ごを食</mark>べます"
+    );
+    // too short for the index
+    let (_, results) = send(&app, search("犬")).await;
+    assert_eq!(found(&results), ["犬"]);
+    // quotes are matched literally rather than read as FTS syntax
+    let (status, results) = send(&app, search("\"りんご OR")).await;
+    assert_eq!(status, StatusCode::OK, "{}", results);
+    assert_eq!(results, json!([]));
+
+    let (_, quiz) = send(
+        &app,
+        TestRequest::get().uri(&format!("/api/quizzes/{}", id)),
+    )
+    .await;
+    let question_id = &quiz["questions"][0]["id"];
+    let (status, patched) = send(
+        &app,
+        TestRequest::patch()
+            .uri(&format!("/api/quizzes/{}/questions/{}", id, question_id))
+            .insert_header(("X-Owner-Token", token.as_str()))
+            .set_json(json!({ "text": "みかんを食べます" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", patched);
+
+    let (_, results) = send(&app, search("りんご")).await;
+    assert_eq!(results, json!([]));
+    let (_, results) = send(&app, search("みかん")).await;
+    assert_eq!(found(&results), ["みかんを食べます"]);
+}
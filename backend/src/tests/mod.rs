@@ -0,0 +1,125 @@
+//! End-to-end tests of the HTTP API.
+//!
+//! Each test serves `build_app`, middleware included, over its own
+//! `sqlite::memory:` database brought up to date by `init_db`, and talks to
+//! it through `actix_web::test`. `state` gives the default setup;
+//! `state_with` takes a changed `test_config` for tests of the limits.
+
+use crate::config::{Config, DatabaseConfig};
+use crate::{cache, db, middleware, readings, AppState};
+use actix_http::Request;
+use actix_web::body::{self, MessageBody};
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use actix_web::web;
+use serde_json::{json, Value as JsonValue};
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+mod crud;
+mod database;
+mod errors;
+mod limits;
+mod listing;
+mod ownership;
+mod questions;
+mod submissions;
+mod validation;
+
+/// The defaults of `.env.example` over a fresh in-memory database, with rate
+/// limiting off so tests can write as often as they like
+pub fn test_config() -> Config {
+    Config {
+        database: DatabaseConfig {
+            url: "sqlite::memory:".to_string(),
+            max_connections: 5,
+            connect_attempts: 1,
+            checkout_timeout: Duration::from_secs(5),
+            health_check_interval: Duration::from_secs(10),
+        },
+        bind_address: Ipv4Addr::LOCALHOST.into(),
+        port: 0,
+        allowed_origins: None,
+        rate_limit_per_minute: 0,
+        max_body_bytes: 1024 * 1024,
+        request_timeout: Some(Duration::from_secs(30)),
+        purge_interval: None,
+        purge_retention_days: 30,
+        kanji_readings_path: None,
+    }
+}
+
+/// Connect to and migrate the database of `config`, and set up the rest of
+/// the shared state as `main` does
+pub async fn state_with(config: Config) -> AppState {
+    let pool = db::connect(&config.database)
+        .await
+        .expect("in-memory database opens");
+    db::init_db(&pool).await.expect("migrations apply");
+    AppState {
+        pool,
+        rate_limiter: web::Data::new(middleware::RateLimiter::new(config.rate_limit_per_minute)),
+        metrics: web::Data::new(middleware::Metrics::new()),
+        question_counts: web::Data::new(cache::QuestionCounts::new()),
+        kanji_readings: web::Data::new(readings::KanjiReadings::default()),
+        db_health: web::Data::new(db::DbHealth::new()),
+        config: web::Data::new(config),
+    }
+}
+
+pub async fn state() -> AppState {
+    state_with(test_config()).await
+}
+
+/// Send `req` and return the status and the JSON body (`Null` when empty).
+/// An error out of the app is rendered as the server would render it.
+pub async fn send<S, B>(app: &S, req: TestRequest) -> (StatusCode, JsonValue)
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let (status, body) = match test::try_call_service(app, req.to_request()).await {
+        Ok(resp) => (resp.status(), test::read_body(resp).await),
+        Err(err) => {
+            let resp = err.error_response();
+            let status = resp.status();
+            let body = body::to_bytes(resp.into_body()).await.unwrap_or_default();
+            (status, body)
+        }
+    };
+    let json = if body.is_empty() {
+        JsonValue::Null
+    } else {
+        serde_json::from_slice(&body).expect("response body is JSON")
+    };
+    (status, json)
+}
+
+/// A valid create body titled `title` with `questions` single-choice
+/// questions, the first option correct
+pub fn quiz_json(title: &str, questions: usize) -> JsonValue {
+    let questions: Vec<JsonValue> = (0..questions)
+        .map(|i| {
+            json!({
+                "text": format!("Question {}", i),
+                "options": [format!("Right {}", i), format!("Wrong {}", i)],
+                "correct_answer": 0
+            })
+        })
+        .collect();
+    json!({ "title": title, "questions": questions })
+}
+
+/// Create a quiz from `body`, returning its id and owner token
+pub async fn create_quiz<S, B>(app: &S, body: JsonValue) -> (i64, String)
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let (status, created) = send(app, TestRequest::post().uri("/api/quizzes").set_json(body)).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", created);
+    let id = created["id"].as_i64().expect("id");
+    let token = created["owner_token"].as_str().expect("owner_token");
+    (id, token.to_string())
+}
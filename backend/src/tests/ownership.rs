@@ -0,0 +1,190 @@
+use super::{create_quiz, quiz_json, send, state};
+use crate::build_app;
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use serde_json::{json, Value as JsonValue};
+
+#[actix_web::test]
+async fn merge_deleting_sources_needs_their_tokens() {
+    let app = test::init_service(build_app(&state().await)).await;
+    let (a, token_a) = create_quiz(&app, quiz_json("A", 1)).await;
+    let (b, token_b) = create_quiz(&app, quiz_json("B", 1)).await;
+    let merge = |token: Option<String>| {
+        let req = TestRequest::post()
+            .uri("/api/quizzes/merge?delete_sources=true")
+            .set_json(json!({ "title": "A and B", "source_ids": [a, b] }));
+        match token {
+            Some(token) => req.insert_header(("X-Owner-Token", token)),
+            None => req,
+        }
+    };
+
+    let (status, _) = send(&app, merge(None)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(&app, merge(Some(token_a.clone()))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    // nothing was merged or deleted by the refused requests
+    let (status, _) = send(&app, TestRequest::get().uri(&format!("/api/quizzes/{}", a))).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send(&app, merge(Some(format!("{}, {}", token_a, token_b)))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = send(&app, TestRequest::get().uri(&format!("/api/quizzes/{}", b))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn question_routes_need_the_token() {
+    let app = test::init_service(build_app(&state().await)).await;
+    let (id, token) = create_quiz(&app, quiz_json("Owned", 2)).await;
+    let (_, quiz) = send(
+        &app,
+        TestRequest::get().uri(&format!("/api/quizzes/{}", id)),
+    )
+    .await;
+    let (first, second) = (&quiz["questions"][0]["id"], &quiz["questions"][1]["id"]);
+    let question = json!({ "text": "New", "options": ["a", "b"], "correct_answer": 0 });
+
+    let requests = [
+        TestRequest::post()
+            .uri(&format!("/api/quizzes/{}/questions", id))
+            .set_json(&question),
+        TestRequest::delete().uri(&format!("/api/quizzes/{}/questions", id)),
+        TestRequest::post()
+            .uri(&format!("/api/quizzes/{}/import/csv", id))
+            .set_payload("text,options,correct_answer\nNew,a|b,0\n"),
+        TestRequest::put()
+            .uri(&format!("/api/quizzes/{}/questions/order", id))
+            .set_json(json!({ "order": [second, first] })),
+        TestRequest::post()
+            .uri(&format!("/api/quizzes/{}/questions/attach", id))
+            .set_json(json!({ "question_ids": [first] })),
+        TestRequest::post()
+            .uri(&format!("/api/quizzes/{}/questions/detach", id))
+            .set_json(json!({ "question_ids": [first] })),
+        TestRequest::patch()
+            .uri(&format!("/api/quizzes/{}/questions/{}", id, first))
+            .set_json(json!({ "text": "Changed" })),
+        TestRequest::delete().uri(&format!("/api/quizzes/{}/questions/{}", id, first)),
+    ];
+    for req in requests {
+        let (status, body) = send(&app, req).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{}", body);
+        assert_eq!(body["code"], "owner_token_required");
+    }
+
+    let (_, quiz) = send(
+        &app,
+        TestRequest::get().uri(&format!("/api/quizzes/{}", id)),
+    )
+    .await;
+    assert_eq!(quiz["questions"][0]["text"], "Question 0");
+    assert_eq!(quiz["questions"].as_array().map(Vec::len), Some(2));
+
+    let (status, _) = send(
+        &app,
+        TestRequest::patch()
+            .uri(&format!("/api/quizzes/{}/questions/{}", id, first))
+            .insert_header(("X-Owner-Token", token.as_str()))
+            .set_json(json!({ "text": "Changed" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn restoring_needs_the_token() {
+    let app = test::init_service(build_app(&state().await)).await;
+    let (id, token) = create_quiz(&app, quiz_json("Owned", 1)).await;
+    let (status, _) = send(
+        &app,
+        TestRequest::delete()
+            .uri(&format!("/api/quizzes/{}", id))
+            .insert_header(("X-Owner-Token", token.as_str())),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let restore = || TestRequest::post().uri(&format!("/api/quizzes/{}/restore", id));
+    let (status, _) = send(&app, restore()).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(&app, restore().insert_header(("X-Owner-Token", token))).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn idempotent_retry_does_not_hand_out_the_token() {
+    let app = test::init_service(build_app(&state().await)).await;
+    let create = || {
+        TestRequest::post()
+            .uri("/api/quizzes")
+            .insert_header(("Idempotency-Key", "create-owned"))
+            .set_json(quiz_json("Retried", 1))
+    };
+
+    let (status, first) = send(&app, create()).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, retry) = send(&app, create()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(retry["id"], first["id"]);
+    assert_eq!(retry["owner_token"], JsonValue::Null);
+
+    let token = first["owner_token"].as_str().expect("owner_token");
+    let (status, _) = send(
+        &app,
+        TestRequest::post()
+            .uri(&format!("/api/quizzes/{}/publish", first["id"]))
+            .insert_header(("X-Owner-Token", token)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn copies_and_merges_get_their_own_token() {
+    let app = test::init_service(build_app(&state().await)).await;
+    let (a, _) = create_quiz(&app, quiz_json("A", 1)).await;
+    let (b, _) = create_quiz(&app, quiz_json("B", 1)).await;
+
+    let (status, duplicated) = send(
+        &app,
+        TestRequest::post().uri(&format!("/api/quizzes/{}/duplicate", a)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", duplicated);
+    let (status, merged) = send(
+        &app,
+        TestRequest::post()
+            .uri("/api/quizzes/merge")
+            .set_json(json!({ "title": "A and B", "source_ids": [a, b] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", merged);
+
+    for created in [duplicated, merged] {
+        let uri = format!("/api/quizzes/{}", created["id"]);
+        let token = created["owner_token"].as_str().expect("owner_token");
+        let patch = || {
+            TestRequest::patch()
+                .uri(&uri)
+                .set_json(json!({ "status": "published" }))
+        };
+
+        let (status, body) = send(&app, patch()).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{}", body);
+        assert_eq!(body["code"], "owner_token_required");
+        let (status, body) = send(&app, TestRequest::delete().uri(&uri)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{}", body);
+
+        let (status, body) = send(&app, patch().insert_header(("X-Owner-Token", token))).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let (status, _) = send(
+            &app,
+            TestRequest::delete()
+                .uri(&uri)
+                .insert_header(("X-Owner-Token", token)),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+}
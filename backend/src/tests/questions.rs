@@ -0,0 +1,159 @@
+use super::{create_quiz, quiz_json, send, state};
+use crate::build_app;
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use serde_json::json;
+
+#[actix_web::test]
+async fn patching_a_question_stores_every_field() {
+    let app = test::init_service(build_app(&state().await)).await;
+    let (id, token) = create_quiz(&app, quiz_json("Patched", 2)).await;
+    let (_, quiz) = send(
+        &app,
+        TestRequest::get().uri(&format!("/api/quizzes/{}", id)),
+    )
+    .await;
+    let question_id = &quiz["questions"][1]["id"];
+
+    let (status, patched) = send(
+        &app,
+        TestRequest::patch()
+            .uri(&format!("/api/quizzes/{}/questions/{}", id, question_id))
+            .insert_header(("X-Owner-Token", token.as_str()))
+            .set_json(json!({
+                "example_sentence": "水を飲みます。",
+                "example_reading": "みずをのみます。",
+                "difficulty": 5
+            })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", patched);
+
+    let (_, quiz) = send(
+        &app,
+        TestRequest::get().uri(&format!("/api/quizzes/{}", id)),
+    )
+    .await;
+    let question = &quiz["questions"][1];
+    assert_eq!(question["example_sentence"], "水を飲みます。");
+    assert_eq!(question["example_reading"], "みずをのみます。");
+    assert_eq!(question["difficulty"], 5);
+    assert_eq!(question["text"], "Question 1");
+    // the order is untouched
+    assert_eq!(quiz["questions"][0]["text"], "Question 0");
+}
+
+#[actix_web::test]
+async fn patching_another_quizs_question_is_not_found() {
+    let app = test::init_service(build_app(&state().await)).await;
+    let (a, token_a) = create_quiz(&app, quiz_json("A", 1)).await;
+    let (b, _) = create_quiz(&app, quiz_json("B", 1)).await;
+    let (_, quiz_b) = send(&app, TestRequest::get().uri(&format!("/api/quizzes/{}", b))).await;
+
+    let (status, body) = send(
+        &app,
+        TestRequest::patch()
+            .uri(&format!(
+                "/api/quizzes/{}/questions/{}",
+                a, quiz_b["questions"][0]["id"]
+            ))
+            .insert_header(("X-Owner-Token", token_a))
+            .set_json(json!({ "text": "Hijacked" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "question_not_found");
+}
+
+#[actix_web::test]
+async fn only_reading_questions_need_a_kana_reading() {
+    let app = test::init_service(build_app(&state().await)).await;
+    // a romaji reading is fine on a choice question
+    let mut body = quiz_json("Romaji", 1);
+    body["questions"][0]["reading"] = json!("mizu");
+    create_quiz(&app, body).await;
+
+    let reading_quiz = |reading: &str| {
+        json!({
+            "title": "Readings",
+            "questions": [{
+                "text": "水",
+                "options": [],
+                "question_type": "reading",
+                "reading": reading
+            }]
+        })
+    };
+    let (status, body) = send(
+        &app,
+        TestRequest::post()
+            .uri("/api/quizzes")
+            .set_json(reading_quiz("mizu")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    assert!(
+        body.to_string().contains("questions[0].reading"),
+        "{}",
+        body
+    );
+
+    let (id, _) = create_quiz(&app, reading_quiz("みず")).await;
+    let (status, result) = send(
+        &app,
+        TestRequest::post()
+            .uri(&format!("/api/quizzes/{}/submit", id))
+            .set_json(json!({ "answers": ["ミズ"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", result);
+    assert_eq!(result["score"], 1);
+}
+
+#[actix_web::test]
+async fn patching_a_question_can_clear_optional_fields() {
+    let app = test::init_service(build_app(&state().await)).await;
+    let mut body = quiz_json("Cleared", 1);
+    body["questions"][0]["reading"] = json!("みず");
+    body["questions"][0]["explanation"] = json!("Water");
+    body["questions"][0]["audio_url"] = json!("https://example.com/mizu.mp3");
+    body["questions"][0]["example_sentence"] = json!("水を飲みます。");
+    let (id, token) = create_quiz(&app, body).await;
+    let (_, quiz) = send(
+        &app,
+        TestRequest::get().uri(&format!("/api/quizzes/{}", id)),
+    )
+    .await;
+    let uri = format!(
+        "/api/quizzes/{}/questions/{}",
+        id, quiz["questions"][0]["id"]
+    );
+    let patch = |body| {
+        TestRequest::patch()
+            .uri(&uri)
+            .insert_header(("X-Owner-Token", token.as_str()))
+            .set_json(body)
+    };
+
+    // omitted fields are kept
+    let (status, patched) = send(&app, patch(json!({ "difficulty": 2 }))).await;
+    assert_eq!(status, StatusCode::OK, "{}", patched);
+    assert_eq!(patched["reading"], "みず");
+    assert_eq!(patched["explanation"], "Water");
+
+    // null and blank both clear
+    let (status, patched) = send(
+        &app,
+        patch(json!({
+            "reading": null,
+            "explanation": "",
+            "audio_url": null,
+            "example_sentence": "  "
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", patched);
+    for field in ["reading", "explanation", "audio_url", "example_sentence"] {
+        assert!(patched[field].is_null(), "{}: {}", field, patched);
+    }
+}
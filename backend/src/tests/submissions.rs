@@ -0,0 +1,34 @@
+use super::{create_quiz, quiz_json, send, state};
+use crate::build_app;
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use serde_json::json;
+
+#[actix_web::test]
+async fn an_out_of_range_answer_names_its_question() {
+    let app = test::init_service(build_app(&state().await)).await;
+    let (id, _) = create_quiz(&app, quiz_json("Bounds", 3)).await;
+    let submit = |answers: serde_json::Value| {
+        TestRequest::post()
+            .uri(&format!("/api/quizzes/{}/submit", id))
+            .set_json(json!({ "answers": answers }))
+    };
+
+    let (status, body) = send(&app, submit(json!([0, 2, -1]))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["question_index"], 1);
+    assert_eq!(
+        body["error"],
+        "Invalid answer 1: question has 2 options, got index 2"
+    );
+
+    let (status, body) = send(&app, submit(json!([0, 1, [0, 5]]))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["question_index"], 2);
+
+    // in range but wrong is scored, not rejected
+    let (status, body) = send(&app, submit(json!([0, 1, 1]))).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["score"], 1);
+    assert_eq!(body["results"], json!([true, false, false]));
+}
@@ -0,0 +1,67 @@
+use super::{create_quiz, quiz_json, send, state};
+use crate::build_app;
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use serde_json::json;
+
+#[actix_web::test]
+async fn a_whitespace_only_title_or_option_is_rejected() {
+    let app = test::init_service(build_app(&state().await)).await;
+    let mut body = quiz_json(" \t\u{3000}", 2);
+    body["questions"][1]["options"][1] = json!("   ");
+
+    let (status, body) = send(&app, TestRequest::post().uri("/api/quizzes").set_json(body)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let errors: Vec<(&str, &str)> = body["errors"]
+        .as_array()
+        .expect("errors")
+        .iter()
+        .map(|e| (e["field"].as_str().unwrap(), e["message"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            ("title", "title must not be empty"),
+            (
+                "questions[1].options[1]",
+                "Invalid question 1: options[1] must not be empty"
+            ),
+        ]
+    );
+    assert_eq!(body["question_index"], 1);
+}
+
+#[actix_web::test]
+async fn duplicate_options_are_rejected() {
+    let app = test::init_service(build_app(&state().await)).await;
+    let mut body = quiz_json("Duplicates", 1);
+    // equal once trimmed
+    body["questions"][0]["options"] = json!(["猫", "犬", " 猫 "]);
+
+    let (status, body) = send(&app, TestRequest::post().uri("/api/quizzes").set_json(body)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["errors"][0]["field"], "questions[0].options[2]");
+    assert_eq!(
+        body["errors"][0]["message"],
+        "Invalid question 0: options[2] duplicates options[0] ('猫')"
+    );
+    assert_eq!(body["question_index"], 0);
+}
+
+#[actix_web::test]
+async fn fields_are_stored_trimmed() {
+    let app = test::init_service(build_app(&state().await)).await;
+    let mut body = quiz_json("  Trimmed  ", 1);
+    body["questions"][0]["text"] = json!(" 猫 ");
+    body["questions"][0]["options"] = json!([" ねこ", "いぬ "]);
+
+    let (id, _) = create_quiz(&app, body).await;
+    let (_, quiz) = send(
+        &app,
+        TestRequest::get().uri(&format!("/api/quizzes/{}", id)),
+    )
+    .await;
+    assert_eq!(quiz["title"], "Trimmed");
+    assert_eq!(quiz["questions"][0]["text"], "猫");
+    assert_eq!(quiz["questions"][0]["options"], json!(["ねこ", "いぬ"]));
+}
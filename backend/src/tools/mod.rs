@@ -0,0 +1,359 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// Unicode NFKC: full-width Latin and digits become ASCII and half-width
+/// katakana become their usual full-width forms
+pub fn nfkc(text: &str) -> String {
+    text.nfkc().collect()
+}
+
+/// Canonical form of a typed answer: NFKC with surrounding whitespace trimmed
+pub fn normalize_answer(text: &str) -> String {
+    nfkc(text).trim().to_string()
+}
+
+/// Key used to spot duplicate quiz titles: NFKC, trimmed and lowercased,
+/// so `ＪＬＰＴ N3`, `jlpt n3` and ` JLPT N3 ` all compare equal
+pub fn title_key(title: &str) -> String {
+    nfkc(title).trim().to_lowercase()
+}
+
+/// Map katakana onto the matching hiragana so one table covers both scripts
+fn to_hiragana(c: char) -> char {
+    if ('\u{30A1}'..='\u{30F6}').contains(&c) {
+        char::from_u32(c as u32 - 0x60).unwrap_or(c)
+    } else {
+        c
+    }
+}
+
+/// Fold katakana onto hiragana so answers typed in either script compare
+/// equal (りんご and リンゴ both become りんご). Everything else, including
+/// the long-vowel mark ー, is left as-is.
+pub fn fold_kana(text: &str) -> String {
+    text.chars().map(to_hiragana).collect()
+}
+
+/// Whether `text` is non-empty and made up only of hiragana, katakana and
+/// the long-vowel mark ー (as typed for a reading); kanji, Latin letters,
+/// digits, spaces and punctuation all fail.
+pub fn is_kana(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| matches!(c, '\u{3041}'..='\u{3096}' | '\u{30A1}'..='\u{30FA}' | 'ー'))
+}
+
+/// Hepburn spelling of a single mora or a yōon/extended digraph (in hiragana)
+fn syllable(kana: &str) -> Option<&'static str> {
+    let romaji = match kana {
+        // Digraphs first: a kana followed by a small ゃゅょ or small vowel
+        "きゃ" => "kya",
+        "きゅ" => "kyu",
+        "きょ" => "kyo",
+        "ぎゃ" => "gya",
+        "ぎゅ" => "gyu",
+        "ぎょ" => "gyo",
+        "しゃ" => "sha",
+        "しゅ" => "shu",
+        "しょ" => "sho",
+        "しぇ" => "she",
+        "じゃ" => "ja",
+        "じゅ" => "ju",
+        "じょ" => "jo",
+        "じぇ" => "je",
+        "ちゃ" => "cha",
+        "ちゅ" => "chu",
+        "ちょ" => "cho",
+        "ちぇ" => "che",
+        "ぢゃ" => "ja",
+        "ぢゅ" => "ju",
+        "ぢょ" => "jo",
+        "にゃ" => "nya",
+        "にゅ" => "nyu",
+        "にょ" => "nyo",
+        "ひゃ" => "hya",
+        "ひゅ" => "hyu",
+        "ひょ" => "hyo",
+        "びゃ" => "bya",
+        "びゅ" => "byu",
+        "びょ" => "byo",
+        "ぴゃ" => "pya",
+        "ぴゅ" => "pyu",
+        "ぴょ" => "pyo",
+        "みゃ" => "mya",
+        "みゅ" => "myu",
+        "みょ" => "myo",
+        "りゃ" => "rya",
+        "りゅ" => "ryu",
+        "りょ" => "ryo",
+        "ふぁ" => "fa",
+        "ふぃ" => "fi",
+        "ふぇ" => "fe",
+        "ふぉ" => "fo",
+        "てぃ" => "ti",
+        "でぃ" => "di",
+        "とぅ" => "tu",
+        "どぅ" => "du",
+        "うぃ" => "wi",
+        "うぇ" => "we",
+        "うぉ" => "wo",
+        "ゔぁ" => "va",
+        "ゔぃ" => "vi",
+        "ゔぇ" => "ve",
+        "ゔぉ" => "vo",
+
+        "あ" => "a",
+        "い" => "i",
+        "う" => "u",
+        "え" => "e",
+        "お" => "o",
+        "か" => "ka",
+        "き" => "ki",
+        "く" => "ku",
+        "け" => "ke",
+        "こ" => "ko",
+        "が" => "ga",
+        "ぎ" => "gi",
+        "ぐ" => "gu",
+        "げ" => "ge",
+        "ご" => "go",
+        "さ" => "sa",
+        "し" => "shi",
+        "す" => "su",
+        "せ" => "se",
+        "そ" => "so",
+        "ざ" => "za",
+        "じ" => "ji",
+        "ず" => "zu",
+        "ぜ" => "ze",
+        "ぞ" => "zo",
+        "た" => "ta",
+        "ち" => "chi",
+        "つ" => "tsu",
+        "て" => "te",
+        "と" => "to",
+        "だ" => "da",
+        "ぢ" => "ji",
+        "づ" => "zu",
+        "で" => "de",
+        "ど" => "do",
+        "な" => "na",
+        "に" => "ni",
+        "ぬ" => "nu",
+        "ね" => "ne",
+        "の" => "no",
+        "は" => "ha",
+        "ひ" => "hi",
+        "ふ" => "fu",
+        "へ" => "he",
+        "ほ" => "ho",
+        "ば" => "ba",
+        "び" => "bi",
+        "ぶ" => "bu",
+        "べ" => "be",
+        "ぼ" => "bo",
+        "ぱ" => "pa",
+        "ぴ" => "pi",
+        "ぷ" => "pu",
+        "ぺ" => "pe",
+        "ぽ" => "po",
+        "ま" => "ma",
+        "み" => "mi",
+        "む" => "mu",
+        "め" => "me",
+        "も" => "mo",
+        "や" => "ya",
+        "ゆ" => "yu",
+        "よ" => "yo",
+        "ら" => "ra",
+        "り" => "ri",
+        "る" => "ru",
+        "れ" => "re",
+        "ろ" => "ro",
+        "わ" => "wa",
+        "ゐ" => "i",
+        "ゑ" => "e",
+        "を" => "o",
+        "ゔ" => "vu",
+        // Small kana on their own (e.g. after a long-vowel mark)
+        "ぁ" => "a",
+        "ぃ" => "i",
+        "ぅ" => "u",
+        "ぇ" => "e",
+        "ぉ" => "o",
+        "ゃ" => "ya",
+        "ゅ" => "yu",
+        "ょ" => "yo",
+        "ゎ" => "wa",
+        "ゕ" => "ka",
+        "ゖ" => "ke",
+        _ => return None,
+    };
+    Some(romaji)
+}
+
+/// Longest syllable starting at `i`, with the number of kana it consumed
+fn syllable_at(chars: &[char], i: usize) -> Option<(&'static str, usize)> {
+    if let Some(&next) = chars.get(i + 1) {
+        let pair: String = [to_hiragana(chars[i]), to_hiragana(next)].iter().collect();
+        if let Some(romaji) = syllable(&pair) {
+            return Some((romaji, 2));
+        }
+    }
+    let single = to_hiragana(*chars.get(i)?).to_string();
+    syllable(&single).map(|romaji| (romaji, 1))
+}
+
+/// Transliterate hiragana and katakana to (modified) Hepburn romaji.
+///
+/// - っ doubles the following consonant (`ch` becomes `tch`) and is dropped
+///   when nothing romanisable follows it.
+/// - ん is always `n`, written `n'` before a vowel or `y` so that e.g.
+///   きんえん (`kin'en`) and きねん (`kinen`) stay distinct.
+/// - ー repeats the previous vowel; long vowels spelled with kana (おう, えい)
+///   are transliterated letter by letter.
+///
+/// Anything that isn't kana (kanji, Latin, punctuation) passes through as-is.
+pub fn kana_to_romaji(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        match to_hiragana(chars[i]) {
+            'っ' => {
+                if let Some((next, _)) = syllable_at(&chars, i + 1) {
+                    if next.starts_with("ch") {
+                        out.push('t');
+                    } else if let Some(c) = next.chars().next().filter(|c| !"aiueo".contains(*c)) {
+                        out.push(c);
+                    }
+                }
+                i += 1;
+            }
+            'ん' => {
+                out.push('n');
+                if let Some((next, _)) = syllable_at(&chars, i + 1) {
+                    if next.starts_with(['a', 'i', 'u', 'e', 'o', 'y']) {
+                        out.push('\'');
+                    }
+                }
+                i += 1;
+            }
+            'ー' => {
+                match out.chars().last().filter(|c| "aiueo".contains(*c)) {
+                    Some(vowel) => out.push(vowel),
+                    None => out.push(chars[i]),
+                }
+                i += 1;
+            }
+            _ => match syllable_at(&chars, i) {
+                Some((romaji, len)) => {
+                    out.push_str(romaji);
+                    i += len;
+                }
+                None => {
+                    out.push(chars[i]);
+                    i += 1;
+                }
+            },
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nfkc_folds_full_width_latin_and_digits_to_ascii() {
+        assert_eq!(nfkc("１２３"), "123");
+        assert_eq!(nfkc("ＪＬＰＴ　Ｎ３"), "JLPT N3");
+    }
+
+    #[test]
+    fn nfkc_widens_half_width_katakana() {
+        assert_eq!(nfkc("ｶﾀｶﾅ"), "カタカナ");
+        // the voiced mark combines with the kana before it
+        assert_eq!(nfkc("ｶﾞｯｺｳ"), "ガッコウ");
+        // full-width katakana and hiragana are already canonical
+        assert_eq!(nfkc("カタカナ"), "カタカナ");
+        assert_eq!(nfkc("ひらがな"), "ひらがな");
+    }
+
+    #[test]
+    fn normalize_answer_trims_after_normalizing() {
+        assert_eq!(normalize_answer("　１２３ "), "123");
+        assert_eq!(normalize_answer(" ﾘﾝｺﾞ\n"), "リンゴ");
+    }
+
+    #[test]
+    fn fold_kana_maps_katakana_onto_hiragana() {
+        assert_eq!(fold_kana("リンゴ"), "りんご");
+        assert_eq!(fold_kana("りんご"), "りんご");
+        assert_eq!(fold_kana("ガッコウ"), "がっこう");
+        // small kana and ヴ have hiragana counterparts too
+        assert_eq!(fold_kana("ヴァイオリン"), "ゔぁいおりん");
+    }
+
+    #[test]
+    fn fold_kana_leaves_everything_else_alone() {
+        assert_eq!(fold_kana("ラーメン"), "らーめん");
+        assert_eq!(fold_kana("日本ゴ"), "日本ご");
+        assert_eq!(fold_kana("ringo"), "ringo");
+        // ヷ has no hiragana form
+        assert_eq!(fold_kana("ヷ"), "ヷ");
+    }
+
+    #[test]
+    fn is_kana_accepts_hiragana_katakana_and_the_long_vowel_mark() {
+        assert!(is_kana("ひらがな"));
+        assert!(is_kana("カタカナ"));
+        assert!(is_kana("らーめん"));
+        assert!(is_kana("にほんゴ"));
+    }
+
+    #[test]
+    fn is_kana_rejects_kanji_latin_and_mixed_input() {
+        assert!(!is_kana(""));
+        assert!(!is_kana("日本語"));
+        assert!(!is_kana("nihongo"));
+        assert!(!is_kana("日本ご"));
+        assert!(!is_kana("にほんgo"));
+        assert!(!is_kana("にほん ご"));
+        assert!(!is_kana("にほん。"));
+    }
+
+    #[test]
+    fn kana_to_romaji_doubles_the_consonant_after_small_tsu() {
+        assert_eq!(kana_to_romaji("がっこう"), "gakkou");
+        assert_eq!(kana_to_romaji("まっちゃ"), "matcha");
+        assert_eq!(kana_to_romaji("ざっし"), "zasshi");
+        assert_eq!(kana_to_romaji("あっ"), "a");
+    }
+
+    #[test]
+    fn kana_to_romaji_spells_long_vowels() {
+        assert_eq!(kana_to_romaji("ラーメン"), "raamen");
+        assert_eq!(kana_to_romaji("コーヒー"), "koohii");
+        assert_eq!(kana_to_romaji("とうきょう"), "toukyou");
+        assert_eq!(kana_to_romaji("せんせい"), "sensei");
+    }
+
+    #[test]
+    fn kana_to_romaji_marks_n_before_vowels_and_y() {
+        assert_eq!(kana_to_romaji("きんえん"), "kin'en");
+        assert_eq!(kana_to_romaji("きねん"), "kinen");
+        assert_eq!(kana_to_romaji("しんよう"), "shin'you");
+        assert_eq!(kana_to_romaji("さんぽ"), "sanpo");
+        assert_eq!(kana_to_romaji("ほん"), "hon");
+    }
+
+    #[test]
+    fn kana_to_romaji_passes_other_text_through() {
+        assert_eq!(kana_to_romaji("日本ご"), "日本go");
+        assert_eq!(kana_to_romaji("N3 テスト"), "N3 tesuto");
+    }
+}
@@ -6,10 +6,13 @@ const BACKEND_URL = process.env.BACKEND_URL || "http://localhost:8081";
 // List all quizzes
 exports.listQuizzes = async (req, res) => {
   try {
-    const response = await axios.get(`${BACKEND_URL}/api/quizzes`);
+    // Backend responds with a page: { quizzes, total, limit, offset }
+    const response = await axios.get(`${BACKEND_URL}/api/quizzes`, {
+      params: { limit: req.query.limit, offset: req.query.offset },
+    });
     res.render("quizList", {
       title: "Japanese Vocabulary Quizzes",
-      quizzes: response.data,
+      quizzes: response.data.quizzes || [],
     });
   } catch (error) {
     console.error("Error fetching quizzes:", error.message);